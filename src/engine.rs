@@ -0,0 +1,201 @@
+use crate::game::{Game, Move, MoveError, SelectedPos};
+
+// Klondike played from code rather than a terminal: the stress run drives it, and it is
+// what scripted games or a solver would sit on
+pub struct Engine {
+    game: Game,
+}
+
+// what a player can do, whatever the keys or clicks that ask for it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    // a card, or the run below it, from one pile onto another
+    Move(SelectedPos, SelectedPos),
+    // the next card or three from the stock, or the discard turned back over
+    Deal,
+    Undo,
+}
+
+impl Engine {
+    pub fn new(seed: u64, draw_count: u8) -> Self {
+        Self { game: Game::new(seed, draw_count) }
+    }
+
+    pub fn state(&self) -> &Game {
+        &self.game
+    }
+
+    // the moves the action made, or for an undo the ones it took back; the board is left
+    // as it was whenever an action is refused
+    pub fn apply(&mut self, action: Action) -> Result<Vec<Move>, MoveError> {
+        match action {
+            Action::Move(src, dest) => Ok(vec![self.game.apply(src, dest)?]),
+            Action::Deal => Ok(vec![self.game.deal().ok_or(MoveError::EmptySource)?]),
+            Action::Undo => {
+                let undone = self.game.undo();
+                if undone.is_empty() {
+                    return Err(MoveError::NothingToUndo);
+                }
+                Ok(undone)
+            }
+        }
+    }
+
+    // the board moves worth making, and a deal when the stock or a redeal allows one
+    pub fn legal_actions(&self) -> Vec<Action> {
+        let mut actions: Vec<Action> = self.game.legal_moves()
+            .into_iter()
+            .map(|(src, dest)| Action::Move(src, dest))
+            .collect();
+        if !self.game.stock.0.is_empty() || self.game.can_recycle() {
+            actions.push(Action::Deal);
+        }
+        actions
+    }
+
+    pub fn is_won(&self) -> bool {
+        self.game.check_win()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures::*;
+
+    #[test]
+    fn scripted_games_keep_the_deck_whole() {
+        for seed in 0..10 {
+            let mut engine = Engine::new(seed, if seed % 2 == 0 {1} else {3});
+            let dealt = engine.state().state_hash();
+            let mut played = 0;
+            // the first legal action each time, with an undo every so often
+            for step in 0..200 {
+                let action = match engine.legal_actions().first() {
+                    _ if step % 7 == 6 => Action::Undo,
+                    Some(&action) => action,
+                    None => break,
+                };
+                if engine.apply(action).is_ok() {
+                    played += 1;
+                }
+                engine.state().assert_invariants();
+            }
+            assert!(played > 0);
+            while engine.apply(Action::Undo).is_ok() {}
+            assert_eq!(engine.state().state_hash(), dealt);
+        }
+    }
+
+    #[test]
+    fn deal_turns_the_stock_over_onto_the_discard() {
+        let mut engine = Engine::new(4, 1);
+        let stock = engine.state().stock.0.len();
+        assert_eq!(engine.apply(Action::Deal), Ok(vec![Move::Draw(1)]));
+        assert_eq!(engine.state().stock.0.len(), stock - 1);
+        assert_eq!(engine.state().discard.0.len(), 1);
+        assert!(!engine.state().discard.0[0].hidden);
+        assert_eq!(engine.state().moves, 0);
+        assert_eq!(engine.apply(Action::Undo), Ok(vec![Move::Draw(1)]));
+        assert_eq!(engine.state().stock.0.len(), stock);
+    }
+
+    #[test]
+    fn refused_actions_leave_the_board_alone() {
+        let mut engine = Engine { game: bare_game() };
+        engine.game.rows[0].0 = vec![face_down(4, 1), card(1, 0)];
+        engine.game.rows[1].0 = vec![card(11, 3)];
+        let start = engine.state().state_hash();
+        assert_eq!(engine.apply(Action::Undo), Err(MoveError::NothingToUndo));
+        assert_eq!(engine.apply(Action::Deal), Err(MoveError::EmptySource));
+        assert_eq!(engine.apply(Action::Move(SelectedPos::Discard, SelectedPos::Column(0, 1))), Err(MoveError::EmptySource));
+        // the two is a rank short of the queen and can't start a foundation
+        assert_eq!(engine.apply(Action::Move(SelectedPos::Column(0, 1), SelectedPos::Column(1, 0))), Err(MoveError::ColumnMismatch));
+        assert_eq!(engine.apply(Action::Move(SelectedPos::Column(0, 1), SelectedPos::SuitPile(0))), Err(MoveError::FoundationMismatch));
+        assert_eq!(engine.state().state_hash(), start);
+        assert_eq!(engine.state().moves, 0);
+    }
+
+    #[test]
+    fn seed_1_opens_two_foundations() {
+        use SelectedPos::{Column, SuitPile};
+        let mut engine = Engine::new(1, 1);
+        // A♦ and A♠ go up, then 10♦ onto J♣ and the two of them onto Q♥
+        let script = [
+            (Action::Move(Column(0, 0), SuitPile(3)), Move::ColumnToFoundation { from: 0, to: 3, revealed: false }),
+            (Action::Move(Column(3, 3), SuitPile(0)), Move::ColumnToFoundation { from: 3, to: 0, revealed: true }),
+            (Action::Move(Column(1, 1), Column(4, 4)), Move::ColumnToColumn { from: 1, to: 4, count: 1, revealed: true }),
+            (Action::Move(Column(4, 4), Column(6, 6)), Move::ColumnToColumn { from: 4, to: 6, count: 2, revealed: true }),
+            (Action::Deal, Move::Draw(1)),
+        ];
+        for (action, recorded) in script {
+            assert_eq!(engine.apply(action), Ok(vec![recorded]));
+            engine.state().assert_invariants();
+        }
+        let game = engine.state();
+        assert_eq!(game.suit_piles.iter().map(|p| p.0.len()).collect::<Vec<_>>(), [1, 0, 0, 1]);
+        assert!(game.rows[0].0.is_empty());
+        assert_eq!((game.score, game.moves), (35, 4));
+        assert!(!engine.is_won());
+        assert!(engine.legal_actions().contains(&Action::Move(Column(5, 5), Column(3, 2))));
+    }
+
+    #[test]
+    fn scripted_endgame_is_won() {
+        use SelectedPos::{Column, Discard, SuitPile};
+        let mut engine = Engine { game: bare_game() };
+        let game = &mut engine.game;
+        for (n, pile) in game.suit_piles.iter_mut().enumerate() {
+            pile.0 = (0..12).map(|number| card(number, n as u8)).collect();
+        }
+        let q = game.suit_piles[3].0.pop().unwrap();
+        game.rows[0].0 = vec![face_down(12, 1), card(12, 0)];
+        game.rows[1].0 = vec![card(12, 2), q];
+        game.stock.0 = vec![face_down(12, 3)];
+        engine.state().assert_invariants();
+
+        let script = [
+            Action::Move(Column(0, 1), SuitPile(0)),
+            Action::Move(Column(0, 0), SuitPile(1)),
+            Action::Move(Column(1, 1), SuitPile(3)),
+            Action::Move(Column(1, 0), SuitPile(2)),
+            Action::Deal,
+            Action::Move(Discard, SuitPile(3)),
+        ];
+        for action in script {
+            assert!(!engine.is_won());
+            assert!(engine.legal_actions().contains(&action), "{action:?}");
+            engine.apply(action).unwrap();
+            engine.state().assert_invariants();
+        }
+        assert!(engine.is_won());
+        assert!(engine.legal_actions().is_empty());
+        // five cards up at 10 each, and 5 for turning over the K♥; the deal isn't a move
+        assert_eq!((engine.state().score, engine.state().moves), (55, 5));
+    }
+
+    #[test]
+    fn a_board_with_nothing_to_play_is_stuck() {
+        let mut engine = Engine { game: bare_game() };
+        let game = &mut engine.game;
+        for (n, pile) in game.suit_piles.iter_mut().enumerate() {
+            pile.0 = (0..9).map(|number| card(number, n as u8)).collect();
+        }
+        // the tens and queens are face-down, so the kings and jacks have nowhere to go
+        for suit in 0..4 {
+            game.rows[suit as usize].0 = vec![face_down(9, suit), card(12, suit)];
+        }
+        game.rows[4].0 = vec![face_down(11, 0), face_down(11, 1), card(10, 0)];
+        game.rows[5].0 = vec![face_down(11, 2), card(10, 1)];
+        game.rows[6].0 = vec![face_down(11, 3), card(10, 2)];
+        game.discard.0 = vec![card(10, 3)];
+        game.redeals_remaining = Some(0);
+        engine.state().assert_invariants();
+
+        assert!(engine.legal_actions().is_empty());
+        assert!(engine.state().is_dead());
+        assert_eq!(engine.apply(Action::Deal), Err(MoveError::EmptySource));
+        assert_eq!(engine.apply(Action::Undo), Err(MoveError::NothingToUndo));
+        assert!(!engine.is_won());
+    }
+}
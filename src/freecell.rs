@@ -0,0 +1,219 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::game::{foundation_takes, is_valid_run, stacks_on, Card, Column, Deck, MoveError, Pile};
+
+// where a FreeCell card can be picked up from or put down
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Spot {
+    Cell(usize),
+    Foundation(usize),
+    // column, and how far down it the picked up run starts
+    Column(usize, usize),
+}
+
+// every card dealt face-up to eight columns, with four cells to park single cards in
+pub struct FreeCell {
+    pub columns: [Column; 8],
+    pub cells: [Option<Card>; 4],
+    pub foundations: [Pile; 4],
+    pub seed: u64,
+    pub moves: u32,
+    // source, destination and card count of each move, for undo
+    history: Vec<(Spot, Spot, usize)>,
+}
+
+impl FreeCell {
+    pub fn new(seed: u64) -> Self {
+        let mut deck = Deck::shuffled(&mut StdRng::seed_from_u64(seed));
+        let mut columns: [Column; 8] = std::array::from_fn(|_| Column(Vec::new()));
+        // a card to each column in turn, so the first four end up with seven
+        for (i, mut card) in deck.deal(52).into_iter().enumerate() {
+            card.hidden = false;
+            columns[i % 8].0.push(card);
+        }
+
+        Self {
+            columns,
+            cells: [None; 4],
+            foundations: [const { Pile(Vec::new()) }; 4],
+            seed,
+            moves: 0,
+            history: Vec::new(),
+        }
+    }
+
+    // the cards picked up at spot, bottom card first
+    pub fn run(&self, spot: Spot) -> &[Card] {
+        match spot {
+            Spot::Cell(n) => self.cells[n].as_slice(),
+            Spot::Foundation(n) => {
+                let pile = &self.foundations[n].0;
+                &pile[pile.len().saturating_sub(1)..]
+            }
+            Spot::Column(x, y) => self.columns[x].0.get(y..).unwrap_or(&[]),
+        }
+    }
+
+    // a run only really moves a card at a time, so its length is limited by the free cells
+    // and empty columns it can be spread over on the way; the destination doesn't count
+    pub fn max_run(&self, to_empty: bool) -> usize {
+        let free = self.cells.iter().filter(|c| c.is_none()).count();
+        let empty = self.columns.iter().filter(|c| c.0.is_empty()).count() - to_empty as usize;
+        (free + 1) << empty
+    }
+
+    pub fn apply(&mut self, src: Spot, dest: Spot) -> Result<(), MoveError> {
+        let run = self.run(src);
+        let Some(&card) = run.first() else {
+            return Err(MoveError::EmptySource);
+        };
+        if !is_valid_run(run) {
+            return Err(MoveError::BrokenRun);
+        }
+        let count = run.len();
+        match dest {
+            Spot::Cell(n) => {
+                if count > 1 {
+                    return Err(MoveError::NotTopCard);
+                }
+                if self.cells[n].is_some() {
+                    return Err(MoveError::CellTaken);
+                }
+            }
+            Spot::Foundation(n) => {
+                if count > 1 {
+                    return Err(MoveError::NotTopCard);
+                }
+                if !foundation_takes(n, self.foundations[n].0.last(), &card) {
+                    return Err(MoveError::FoundationMismatch);
+                }
+            }
+            Spot::Column(x, _) => {
+                if matches!(src, Spot::Column(sx, _) if sx == x) {
+                    return Err(MoveError::SameColumn);
+                }
+                let to = &self.columns[x].0;
+                if to.last().is_some_and(|top| !stacks_on(top, &card)) {
+                    return Err(MoveError::ColumnMismatch);
+                }
+                if count > self.max_run(to.is_empty()) {
+                    return Err(MoveError::TooManyCards);
+                }
+            }
+        }
+        self.shift(src, dest, count);
+        self.history.push((src, dest, count));
+        self.moves += 1;
+        Ok(())
+    }
+
+    // moves the top count cards between two spots, without checking the rules
+    fn shift(&mut self, from: Spot, to: Spot, count: usize) {
+        let cards: Vec<Card> = match from {
+            Spot::Cell(n) => self.cells[n].take().into_iter().collect(),
+            Spot::Foundation(n) => self.foundations[n].0.pop().into_iter().collect(),
+            Spot::Column(x, _) => {
+                let col = &mut self.columns[x].0;
+                col.split_off(col.len() - count)
+            }
+        };
+        match to {
+            Spot::Cell(n) => self.cells[n] = cards.first().copied(),
+            Spot::Foundation(n) => self.foundations[n].0.extend(cards),
+            Spot::Column(x, _) => self.columns[x].0.extend(cards),
+        }
+    }
+
+    pub fn undo(&mut self) {
+        if let Some((src, dest, count)) = self.history.pop() {
+            self.shift(dest, src, count);
+        }
+    }
+
+    pub fn foundation_for(&self, card: &Card) -> Option<usize> {
+        (0..4).find(|&n| foundation_takes(n, self.foundations[n].0.last(), card))
+    }
+
+    pub fn check_win(&self) -> bool {
+        self.foundations.iter().all(|p| p.0.len() == 13)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures::card;
+
+    // K♠ down to 7♠ on the first column, Q♦ and K♣ to play it onto,
+    // and a deuce on each column after that so none is empty
+    fn board() -> FreeCell {
+        let mut game = FreeCell::new(1);
+        game.columns[0].0 = (6..13).rev().map(|n| card(n, if n % 2 == 0 {0} else {1})).collect();
+        game.columns[1].0 = vec![card(11, 3)];
+        game.columns[2].0 = vec![card(12, 2)];
+        for x in 3..8 {
+            game.columns[x].0 = vec![card(1, (x % 4) as u8)];
+        }
+        game
+    }
+
+    #[test]
+    fn cells_take_one_card_each() {
+        let mut game = board();
+        assert_eq!(game.apply(Spot::Column(3, 0), Spot::Cell(0)), Ok(()));
+        assert_eq!(game.apply(Spot::Column(4, 0), Spot::Cell(0)), Err(MoveError::CellTaken));
+        assert_eq!(game.apply(Spot::Column(0, 5), Spot::Cell(1)), Err(MoveError::NotTopCard));
+        assert_eq!(game.apply(Spot::Cell(0), Spot::Column(3, 0)), Ok(()));
+        assert!(game.cells.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn foundations_build_up_by_suit() {
+        let mut game = board();
+        game.columns[3].0 = vec![card(1, 1), card(0, 1)];
+        assert_eq!(game.apply(Spot::Column(3, 1), Spot::Foundation(0)), Err(MoveError::FoundationMismatch));
+        assert_eq!(game.apply(Spot::Column(3, 1), Spot::Foundation(1)), Ok(()));
+        assert_eq!(game.apply(Spot::Column(3, 0), Spot::Foundation(1)), Ok(()));
+        assert_eq!(game.foundations[1].0.len(), 2);
+        game.undo();
+        assert_eq!(game.foundations[1].0.len(), 1);
+    }
+
+    #[test]
+    fn runs_are_limited_by_free_cells() {
+        let mut game = board();
+        // four free cells and no empty column: five cards
+        assert_eq!(game.max_run(false), 5);
+        assert_eq!(game.apply(Spot::Column(0, 1), Spot::Column(2, 0)), Err(MoveError::TooManyCards));
+        assert_eq!(game.apply(Spot::Column(0, 2), Spot::Column(1, 0)), Ok(()));
+
+        let mut game = board();
+        game.cells[0] = Some(card(5, 2));
+        game.cells[1] = Some(card(5, 0));
+        assert_eq!(game.max_run(false), 3);
+        assert_eq!(game.apply(Spot::Column(0, 2), Spot::Column(1, 0)), Err(MoveError::TooManyCards));
+        // the 9♠ 8♥ 7♠ onto a 10♦ is three cards, just within it
+        game.columns[3].0 = vec![card(9, 3)];
+        assert_eq!(game.apply(Spot::Column(0, 4), Spot::Column(3, 0)), Ok(()));
+    }
+
+    #[test]
+    fn empty_columns_double_the_run_except_the_one_moved_to() {
+        let mut game = board();
+        game.cells = [Some(card(5, 2)), Some(card(5, 0)), Some(card(7, 2)), Some(card(7, 0))];
+        game.columns[6].0.clear();
+        game.columns[7].0.clear();
+        assert_eq!(game.max_run(false), 4);
+        assert_eq!(game.max_run(true), 2);
+
+        assert_eq!(game.apply(Spot::Column(0, 4), Spot::Column(7, 0)), Err(MoveError::TooManyCards));
+        assert_eq!(game.apply(Spot::Column(0, 5), Spot::Column(7, 0)), Ok(()));
+        assert_eq!(game.columns[7].0.len(), 2);
+        // one empty column left, and it would be the destination
+        assert_eq!(game.max_run(true), 1);
+        assert_eq!(game.apply(Spot::Column(0, 4), Spot::Column(6, 0)), Ok(()));
+        // and with none left, the 8♥ 7♠ can't go back onto the 9♠
+        assert_eq!(game.max_run(false), 1);
+        assert_eq!(game.apply(Spot::Column(7, 0), Spot::Column(6, 0)), Err(MoveError::TooManyCards));
+    }
+}
@@ -0,0 +1,1287 @@
+use std::{fmt, hash::{DefaultHasher, Hash, Hasher}};
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Card {
+    pub suit: u8,
+    pub number: u8,
+    pub hidden: bool,
+    pub selected: bool
+}
+
+impl Card {
+    pub const NUMBERS: [&'static str; 13] = [
+        "A",
+        "2",
+        "3",
+        "4",
+        "5",
+        "6",
+        "7",
+        "8",
+        "9",
+        "10",
+        "J",
+        "Q",
+        "K",
+    ];
+
+    pub const SUITS: [&'static str; 4] = [
+        "♠",
+        "♥",
+        "♣",
+        "♦",
+    ];
+
+    // stand-ins for SUITS where the glyphs won't render
+    pub const SUIT_LETTERS: [&'static str; 4] = [
+        "S",
+        "H",
+        "C",
+        "D",
+    ];
+
+    pub fn color(&self) -> u8 {
+        self.suit % 2
+    }
+
+    pub fn face(&self) -> String {
+        format!(
+            "{}{}",
+            Card::NUMBERS[self.number as usize],
+            Card::SUITS[self.suit as usize]
+        )
+    }
+
+    pub fn ascii_face(&self) -> String {
+        format!(
+            "{}{}",
+            Card::NUMBERS[self.number as usize],
+            Card::SUIT_LETTERS[self.suit as usize]
+        )
+    }
+
+    // the ascii face, then - if hidden and * if selected, as written to save files
+    pub fn token(&self) -> String {
+        let mut token = self.ascii_face();
+        if self.hidden {
+            token.push('-');
+        }
+        if self.selected {
+            token.push('*');
+        }
+        token
+    }
+
+    pub fn parse_token(token: &str) -> Option<Self> {
+        let (token, selected) = token.strip_suffix('*').map_or((token, false), |t| (t, true));
+        let (token, hidden) = token.strip_suffix('-').map_or((token, false), |t| (t, true));
+        let split = token.len().checked_sub(1).filter(|&i| token.is_char_boundary(i))?;
+        let (number, suit) = token.split_at(split);
+        Some(Card {
+            suit: Card::SUIT_LETTERS.iter().position(|&l| l == suit)? as u8,
+            number: Card::NUMBERS.iter().position(|&n| n == number)? as u8,
+            hidden,
+            selected,
+        })
+    }
+
+    // the single code point from the Unicode playing cards block
+    pub fn glyph(&self) -> char {
+        let base = [0x1F0A0, 0x1F0B0, 0x1F0D0, 0x1F0C0][self.suit as usize];
+        // the block has a Knight between Jack and Queen
+        let rank = match self.number {
+            0..=10 => self.number as u32 + 1,
+            n => n as u32 + 2,
+        };
+        char::from_u32(base + rank).unwrap()
+    }
+}
+
+// hidden cards show nothing
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.hidden {
+            return Ok(());
+        }
+        write!(f, "{}{}", Card::NUMBERS[self.number as usize], Card::SUITS[self.suit as usize])
+    }
+}
+
+// face-down cards waiting to be dealt, from the top
+pub struct Deck(Vec<Card>);
+
+impl Deck {
+    pub fn shuffled(rng: &mut StdRng) -> Self {
+        let mut cards: Vec<Card> = (0..52)
+            .map(|i| Card { suit: i % 4, number: i / 4, hidden: true, selected: false })
+            .collect();
+        cards.shuffle(rng);
+        Self(cards)
+    }
+
+    // two decks' worth of spades, for one-suit Spider
+    pub fn spider(rng: &mut StdRng) -> Self {
+        let mut cards: Vec<Card> = (0..104)
+            .map(|i| Card { suit: 0, number: i % 13, hidden: true, selected: false })
+            .collect();
+        cards.shuffle(rng);
+        Self(cards)
+    }
+
+    // the next n cards, or as many as are left
+    pub fn deal(&mut self, n: usize) -> Vec<Card> {
+        self.0.drain(..n.min(self.0.len())).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+#[derive(Clone)]
+pub struct Column(pub Vec<Card>);
+
+// foundation n builds up its own suit (in SUITS order) from the ace
+pub fn foundation_takes(n: usize, top: Option<&Card>, card: &Card) -> bool {
+    match top {
+        Some(top) => top.suit == card.suit && top.number + 1 == card.number,
+        None => card.number == 0 && card.suit as usize == n,
+    }
+}
+
+// the tableau builds down in alternating colors, on face-up cards only
+pub fn stacks_on(top: &Card, card: &Card) -> bool {
+    !top.hidden && top.color() != card.color() && top.number == card.number + 1
+}
+
+// face-up and stepping down one rank at a time in alternating colors, as a moved run must be
+pub fn is_valid_run(run: &[Card]) -> bool {
+    run.iter().all(|c| !c.hidden) &&
+    run.windows(2).all(|w| w[0].color() != w[1].color() && w[0].number == w[1].number + 1)
+}
+
+#[derive(Clone)]
+pub struct Pile(pub Vec<Card>);
+
+// entries are small, a Move each rather than a copy of the board, so even a long game
+// keeps its whole history; a lower limit saves a little memory but forgets older moves
+pub const UNDO_LIMIT: usize = 200;
+
+// where the next recorded move goes on the undo stack
+#[derive(Clone, Copy, PartialEq)]
+enum Grouping {
+    Off,
+    // the next move starts an entry that the ones after it join
+    Starting,
+    Joining,
+}
+
+// everything needed to reverse one change to the board
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Move {
+    // how many cards were turned
+    Draw(usize),
+    Recycle,
+    DiscardToFoundation(usize),
+    DiscardToColumn(usize),
+    // revealed: the card left behind in the source column was turned face-up
+    ColumnToFoundation { from: usize, to: usize, revealed: bool },
+    FoundationToColumn { from: usize, to: usize },
+    ColumnToColumn { from: usize, to: usize, count: usize, revealed: bool },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScoringMode {
+    Standard,
+    // the deck is bought for 52 and every card sent up pays 5
+    Vegas,
+}
+
+impl ScoringMode {
+    // what a fresh deal's score starts from
+    pub fn buy_in(&self) -> i32 {
+        match self {
+            ScoringMode::Standard => 0,
+            ScoringMode::Vegas => -52,
+        }
+    }
+}
+
+impl Move {
+    // cards moved, or turned over by a deal; a recycle turns the whole discard over
+    pub fn cards(&self) -> usize {
+        match *self {
+            Move::Draw(count) | Move::ColumnToColumn { count, .. } => count,
+            Move::Recycle => 0,
+            _ => 1,
+        }
+    }
+
+    // points under the given rules, taken back again on undo
+    pub fn score(&self, scoring: ScoringMode) -> i32 {
+        if scoring == ScoringMode::Vegas {
+            return match *self {
+                Move::DiscardToFoundation(_) | Move::ColumnToFoundation { .. } => 5,
+                Move::FoundationToColumn { .. } => -5,
+                _ => 0,
+            };
+        }
+        let flip = |revealed: bool| if revealed {5} else {0};
+        match *self {
+            Move::Draw(_) | Move::Recycle => 0,
+            Move::DiscardToFoundation(_) => 10,
+            Move::DiscardToColumn(_) => 5,
+            Move::ColumnToFoundation { revealed, .. } => 10 + flip(revealed),
+            Move::FoundationToColumn { .. } => -15,
+            Move::ColumnToColumn { revealed, .. } => flip(revealed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MoveError {
+    NoSelection,
+    InvalidDestination,
+    EmptySource,
+    SameColumn,
+    NotTopCard,
+    FoundationMismatch,
+    ColumnMismatch,
+    BrokenRun,
+    FaceDown,
+    CellTaken,
+    TooManyCards,
+    ColumnEmpty,
+    NothingToUndo,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MoveError::NoSelection => "nothing selected",
+            MoveError::InvalidDestination => "can't move there",
+            MoveError::EmptySource => "source is empty",
+            MoveError::SameColumn => "same column",
+            MoveError::NotTopCard => "only the bottom card can go up",
+            MoveError::FoundationMismatch => "needs same suit, one rank higher",
+            MoveError::ColumnMismatch => "needs opposite color, one rank lower",
+            MoveError::BrokenRun => "the cards below aren't an ordered run",
+            MoveError::FaceDown => "that card is face down",
+            MoveError::CellTaken => "that cell is taken",
+            MoveError::TooManyCards => "not enough free cells to move that many",
+            MoveError::ColumnEmpty => "fill every column before dealing",
+            MoveError::NothingToUndo => "nothing to undo",
+        })
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum SelectedPos {
+    None,
+    // only ever clicked, never left selected
+    Stock,
+    Discard,
+    SuitPile(usize),
+    Column(usize, usize)
+}
+
+impl SelectedPos {
+    pub fn token(&self) -> String {
+        match self {
+            SelectedPos::None => "none".to_string(),
+            SelectedPos::Stock => "stock".to_string(),
+            SelectedPos::Discard => "discard".to_string(),
+            SelectedPos::SuitPile(n) => format!("foundation {n}"),
+            SelectedPos::Column(x, y) => format!("column {x} {y}"),
+        }
+    }
+
+    pub fn parse_token(token: &str) -> Option<Self> {
+        let mut words = token.split_whitespace();
+        let kind = words.next()?;
+        let mut index = || words.next()?.parse().ok();
+        match kind {
+            "none" => Some(SelectedPos::None),
+            "stock" => Some(SelectedPos::Stock),
+            "discard" => Some(SelectedPos::Discard),
+            "foundation" => Some(SelectedPos::SuitPile(index().filter(|&n| n < 4)?)),
+            "column" => Some(SelectedPos::Column(index().filter(|&x| x < 7)?, index()?)),
+            _ => None,
+        }
+    }
+}
+
+// the cards and the rules, with nothing about how they are shown or played
+#[derive(Clone)]
+pub struct Game {
+    pub rows: [Column; 7],
+    pub stock: Pile,
+    pub discard: Pile,
+    pub suit_piles: [Pile; 4],
+    // one entry per undo: a single move, or a group taken back together
+    pub undo_stack: Vec<Vec<Move>>,
+    // entries kept before the oldest is dropped, at least one
+    pub undo_limit: usize,
+    grouping: Grouping,
+    // a line of play put aside with save_branch, board and undo history, to come back to
+    pub branch: Option<Box<Game>>,
+    pub seed: u64,
+    // cards turned per deal, 1 or 3
+    pub draw_count: u8,
+    // times the discard may still be turned back over, None for no limit
+    pub redeals_remaining: Option<u8>,
+    // successful card moves only: deals and turning the discard over don't count
+    pub moves: u32,
+    pub score: i32,
+    pub scoring: ScoringMode,
+}
+
+impl Game {
+    // the same seed always gives the same deal
+    pub fn new(seed: u64, draw_count: u8) -> Self {
+        let mut deck = Deck::shuffled(&mut StdRng::seed_from_u64(seed));
+
+        let rows = std::array::from_fn(|i| {
+            let mut col = Column(deck.deal(i + 1));
+            col.0[i].hidden = false;
+            col
+        });
+
+        Self {
+            rows,
+            stock: Pile(deck.deal(deck.len())),
+            discard: Pile(Vec::new()),
+            suit_piles: [const { Pile(Vec::new()) }; 4],
+            undo_stack: Vec::new(),
+            undo_limit: UNDO_LIMIT,
+            grouping: Grouping::Off,
+            branch: None,
+            seed,
+            draw_count,
+            redeals_remaining: None,
+            moves: 0,
+            score: 0,
+            scoring: ScoringMode::Standard,
+        }
+    }
+
+    // only meant for a fresh deal, as the score starts over from the buy-in
+    pub fn set_scoring(&mut self, scoring: ScoringMode) {
+        self.scoring = scoring;
+        self.score = scoring.buy_in();
+    }
+
+    // deals the next stock card (or three), or turns the discard back over once the stock runs out;
+    // None when neither is possible
+    pub fn deal(&mut self) -> Option<Move> {
+        let mv = if !self.stock.0.is_empty() {
+            let count = self.stock.0.len().min(self.draw_count as usize);
+            for _ in 0..count {
+                let mut card = self.stock.0.pop().unwrap();
+                card.hidden = false;
+                self.discard.0.push(card);
+            }
+            Move::Draw(count)
+        } else if self.can_recycle() {
+            self.redeals_remaining = self.redeals_remaining.map(|n| n - 1);
+            self.stock.0.extend(self.discard.0.drain(..).rev());
+            for c in &mut self.stock.0 {
+                c.hidden = true;
+            }
+            Move::Recycle
+        } else {
+            return None;
+        };
+        self.record(mv);
+        Some(mv)
+    }
+
+    pub fn can_recycle(&self) -> bool {
+        self.stock.0.is_empty() && !self.discard.0.is_empty() && self.redeals_remaining != Some(0)
+    }
+
+    // (suit, number) each foundation takes next, None once complete; an empty one wants its own suit's ace
+    pub fn next_foundation_needs(&self) -> [Option<(u8, u8)>; 4] {
+        let mut needs = [None; 4];
+        for (n, pile) in self.suit_piles.iter().enumerate() {
+            needs[n] = match pile.0.last() {
+                Some(top) if top.number == 12 => None,
+                Some(top) => Some((top.suit, top.number + 1)),
+                None => Some((n as u8, 0)),
+            };
+        }
+        needs
+    }
+
+    pub fn foundation_for(&self, card: &Card) -> Option<usize> {
+        (0..4).find(|&n| self.validate_suit(n, card))
+    }
+
+    // the move as recorded for undo, or why it was refused
+    pub fn apply(&mut self, src: SelectedPos, dest: SelectedPos) -> Result<Move, MoveError> {
+        let mv = self.handle_move(src, dest)?;
+        self.moves += 1;
+        Ok(mv)
+    }
+
+    pub fn handle_move(&mut self, src: SelectedPos, dest: SelectedPos) -> Result<Move, MoveError> {
+        if src == SelectedPos::None {
+            return Err(MoveError::NoSelection);
+        }
+
+        match dest {
+            SelectedPos::None | SelectedPos::Stock | SelectedPos::Discard => Err(MoveError::InvalidDestination),
+            SelectedPos::SuitPile(n) => {
+                match src {
+                    SelectedPos::Discard => {
+                        let card = *self.discard.0.last().ok_or(MoveError::EmptySource)?;
+                        if !self.validate_suit(n, &card) {
+                            return Err(MoveError::FoundationMismatch);
+                        }
+                        self.discard.0.pop();
+                        self.suit_piles[n].0.push(card);
+                        let mv = Move::DiscardToFoundation(n);
+                        self.record(mv);
+                        Ok(mv)
+                    }
+                    SelectedPos::Column(x, y) => {
+                        // a stale selection can point past the end of the column
+                        let Some(&card) = self.rows[x].0.get(y) else {
+                            return Err(MoveError::EmptySource);
+                        };
+                        if card.hidden {
+                            return Err(MoveError::FaceDown);
+                        }
+                        if !self.is_bottom_card(x, y) {
+                            return Err(MoveError::NotTopCard);
+                        }
+                        if !self.validate_suit(n, &card) {
+                            return Err(MoveError::FoundationMismatch);
+                        }
+                        self.rows[x].0.pop();
+                        self.suit_piles[n].0.push(card);
+
+                        let revealed = self.reveal_top(x);
+                        let mv = Move::ColumnToFoundation { from: x, to: n, revealed };
+                        self.record(mv);
+                        Ok(mv)
+                    }
+                    SelectedPos::None | SelectedPos::Stock | SelectedPos::SuitPile(_) => Err(MoveError::InvalidDestination),
+                }
+            }
+            SelectedPos::Column(x, _) => {
+                match src {
+                    SelectedPos::None => Err(MoveError::NoSelection),
+                    SelectedPos::Stock => Err(MoveError::InvalidDestination),
+                    SelectedPos::Discard => {
+                        let card = *self.discard.0.last().ok_or(MoveError::EmptySource)?;
+                        if !self.validate_col(x, &card) {
+                            return Err(MoveError::ColumnMismatch);
+                        }
+                        self.discard.0.pop();
+                        self.rows[x].0.push(card);
+                        let mv = Move::DiscardToColumn(x);
+                        self.record(mv);
+                        Ok(mv)
+                    },
+                    SelectedPos::SuitPile(n) => {
+                        let card = *self.suit_piles[n].0.last().ok_or(MoveError::EmptySource)?;
+                        if !self.validate_col(x, &card) {
+                            return Err(MoveError::ColumnMismatch);
+                        }
+                        self.suit_piles[n].0.pop();
+                        self.rows[x].0.push(card);
+                        let mv = Move::FoundationToColumn { from: n, to: x };
+                        self.record(mv);
+                        Ok(mv)
+                    },
+                    SelectedPos::Column(sx, sy) => {
+                        if sx == x {
+                            return Err(MoveError::SameColumn);
+                        }
+                        let Some(card) = self.rows[sx].0.get(sy) else {
+                            return Err(MoveError::EmptySource);
+                        };
+                        if card.hidden {
+                            return Err(MoveError::FaceDown);
+                        }
+                        if !is_valid_run(&self.rows[sx].0[sy..]) {
+                            return Err(MoveError::BrokenRun);
+                        }
+                        if !self.validate_col(x, card) {
+                            return Err(MoveError::ColumnMismatch);
+                        }
+                        let tmp: Vec<Card> = self.rows[sx].0.drain(sy..).collect();
+                        let count = tmp.len();
+                        self.rows[x].0.extend(tmp);
+
+                        let revealed = self.reveal_top(sx);
+                        let mv = Move::ColumnToColumn { from: sx, to: x, count, revealed };
+                        self.record(mv);
+                        Ok(mv)
+                    },
+                }
+            },
+        }
+    }
+
+    // turns the card left at the bottom of a column face-up, reporting whether it was hidden
+    fn reveal_top(&mut self, x: usize) -> bool {
+        match self.rows[x].0.last_mut() {
+            Some(card) if card.hidden => {
+                card.hidden = false;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn record(&mut self, mv: Move) {
+        match (self.grouping, self.undo_stack.last_mut()) {
+            (Grouping::Joining, Some(group)) => group.push(mv),
+            _ => {
+                if self.undo_stack.len() >= self.undo_limit {
+                    self.undo_stack.drain(..=self.undo_stack.len() - self.undo_limit);
+                }
+                self.undo_stack.push(vec![mv]);
+            }
+        }
+        if self.grouping == Grouping::Starting {
+            self.grouping = Grouping::Joining;
+        }
+        self.score += mv.score(self.scoring);
+    }
+
+    // moves from here to end_group are taken back by a single undo; joining adds them
+    // to the entry of the move before instead of starting one of their own
+    pub fn begin_group(&mut self, join: bool) {
+        self.grouping = if join {Grouping::Joining} else {Grouping::Starting};
+    }
+
+    pub fn end_group(&mut self) {
+        self.grouping = Grouping::Off;
+    }
+
+    // puts the board back exactly as it was before the last move, or group of moves, and
+    // hands back the moves taken back; empty when there was nothing to undo
+    pub fn undo(&mut self) -> Vec<Move> {
+        let group = self.undo_stack.pop().unwrap_or_default();
+        for &mv in group.iter().rev() {
+            self.undo_move(mv);
+        }
+        group
+    }
+
+    // keeps the game as it stands, so another line can be tried from an earlier move
+    pub fn save_branch(&mut self) {
+        let mut line = self.clone();
+        line.branch = None;
+        self.branch = Some(Box::new(line));
+    }
+
+    // back to the saved line, which stays saved for the next try; false when there is none
+    pub fn return_to_branch(&mut self) -> bool {
+        let Some(line) = self.branch.take() else {
+            return false;
+        };
+        *self = (*line).clone();
+        self.branch = Some(line);
+        true
+    }
+
+    fn undo_move(&mut self, mv: Move) {
+        self.score -= mv.score(self.scoring);
+        match mv {
+            Move::Draw(count) => {
+                for _ in 0..count {
+                    let mut card = self.discard.0.pop().unwrap();
+                    card.hidden = true;
+                    self.stock.0.push(card);
+                }
+            }
+            Move::Recycle => {
+                self.redeals_remaining = self.redeals_remaining.map(|n| n + 1);
+                self.discard.0.extend(self.stock.0.drain(..).rev());
+                for c in &mut self.discard.0 {
+                    c.hidden = false;
+                }
+            }
+            Move::DiscardToFoundation(n) => {
+                self.discard.0.push(self.suit_piles[n].0.pop().unwrap());
+            }
+            Move::DiscardToColumn(x) => {
+                self.discard.0.push(self.rows[x].0.pop().unwrap());
+            }
+            Move::ColumnToFoundation { from, to, revealed } => {
+                self.hide_top(from, revealed);
+                self.rows[from].0.push(self.suit_piles[to].0.pop().unwrap());
+            }
+            Move::FoundationToColumn { from, to } => {
+                self.suit_piles[from].0.push(self.rows[to].0.pop().unwrap());
+            }
+            Move::ColumnToColumn { from, to, count, revealed } => {
+                self.hide_top(from, revealed);
+                let start = self.rows[to].0.len() - count;
+                let tmp: Vec<Card> = self.rows[to].0.drain(start..).collect();
+                self.rows[from].0.extend(tmp);
+            }
+        }
+    }
+
+    fn hide_top(&mut self, x: usize, revealed: bool) {
+        if revealed {
+            if let Some(card) = self.rows[x].0.last_mut() {
+                card.hidden = true;
+            }
+        }
+    }
+
+    pub fn validate_suit(&self, pile_n: usize, card: &Card) -> bool {
+        foundation_takes(pile_n, self.suit_piles[pile_n].0.last(), card)
+    }
+
+    pub fn validate_col(&self, col_n: usize, card: &Card) -> bool {
+        match self.rows[col_n].0.last() {
+            Some(last) => stacks_on(last, card),
+            None => card.number == 12, // King
+        }
+    }
+
+    // only a column's last card, face up, may go up to a foundation
+    pub fn is_bottom_card(&self, x: usize, y: usize) -> bool {
+        self.rows[x].0.get(y).is_some_and(|card| !card.hidden && y + 1 == self.rows[x].0.len())
+    }
+
+    // what stays selected after clicking pos: face-down cards and empty piles are only ever destinations
+    pub fn selectable(&self, pos: SelectedPos) -> SelectedPos {
+        let card = match pos {
+            SelectedPos::Column(x, y) => self.rows[x].0.get(y),
+            SelectedPos::Discard => self.discard.0.last(),
+            SelectedPos::SuitPile(n) => self.suit_piles[n].0.last(),
+            SelectedPos::None | SelectedPos::Stock => None,
+        };
+        match card {
+            Some(card) if !card.hidden => pos,
+            _ => SelectedPos::None,
+        }
+    }
+
+    // everywhere the card (or run) at src may legally go, useful or not
+    pub fn legal_destinations(&self, src: SelectedPos) -> Vec<SelectedPos> {
+        // only a lone card may go up to a foundation, and foundations don't swap between themselves
+        let (card, to_foundation) = match src {
+            SelectedPos::Discard => (self.discard.0.last(), true),
+            SelectedPos::Column(x, y) => {
+                let card = self.rows[x].0.get(y).filter(|_| is_valid_run(&self.rows[x].0[y..]));
+                (card, self.is_bottom_card(x, y))
+            }
+            SelectedPos::SuitPile(n) => (self.suit_piles[n].0.last(), false),
+            SelectedPos::None | SelectedPos::Stock => (None, false),
+        };
+        let Some(card) = card else {
+            return Vec::new();
+        };
+
+        let mut dests = Vec::new();
+        if to_foundation {
+            dests.extend((0..4).filter(|&n| self.validate_suit(n, card)).map(SelectedPos::SuitPile));
+        }
+        for x in 0..7 {
+            if matches!(src, SelectedPos::Column(sx, _) if sx == x) {
+                continue;
+            }
+            if self.validate_col(x, card) {
+                dests.push(SelectedPos::Column(x, self.rows[x].0.len().saturating_sub(1)));
+            }
+        }
+        dests
+    }
+
+    // stock deals are not included, and neither are moves that can't make progress:
+    // pulling cards off the foundations or shifting a run between equivalent spots
+    pub fn legal_moves(&self) -> Vec<(SelectedPos, SelectedPos)> {
+        let mut moves = Vec::new();
+        let col_dest = |x: usize| SelectedPos::Column(x, self.rows[x].0.len().saturating_sub(1));
+
+        if let Some(card) = self.discard.0.last() {
+            for n in 0..4 {
+                if self.validate_suit(n, card) {
+                    moves.push((SelectedPos::Discard, SelectedPos::SuitPile(n)));
+                }
+            }
+            for x in 0..7 {
+                if self.validate_col(x, card) {
+                    moves.push((SelectedPos::Discard, col_dest(x)));
+                }
+            }
+        }
+
+        for (sx, col) in self.rows.iter().enumerate() {
+            let Some(last) = col.0.last() else {
+                continue;
+            };
+            let bottom = col.0.len() - 1;
+            for n in 0..4 {
+                if self.is_bottom_card(sx, bottom) && self.validate_suit(n, last) {
+                    moves.push((SelectedPos::Column(sx, bottom), SelectedPos::SuitPile(n)));
+                }
+            }
+
+            for (sy, card) in col.0.iter().enumerate() {
+                if !is_valid_run(&col.0[sy..]) {
+                    continue;
+                }
+                // worth moving if it empties the column, reveals a card or frees one for a foundation
+                let useful = match sy.checked_sub(1) {
+                    None => true,
+                    Some(i) => col.0[i].hidden || (0..4).any(|n| self.validate_suit(n, &col.0[i])),
+                };
+                if !useful {
+                    continue;
+                }
+                for x in 0..7 {
+                    if x == sx || (sy == 0 && self.rows[x].0.is_empty()) {
+                        continue;
+                    }
+                    if self.validate_col(x, card) {
+                        moves.push((SelectedPos::Column(sx, sy), col_dest(x)));
+                    }
+                }
+            }
+        }
+
+        moves
+    }
+
+    // nothing to play now, nor after dealing through the stock as often as it takes
+    pub fn is_dead(&self) -> bool {
+        let mut sim = self.clone();
+        // the first recycle may regroup the draw-three deals, after that every pass repeats
+        let mut recycled = false;
+        loop {
+            if !sim.legal_moves().is_empty() {
+                return false;
+            }
+            if sim.stock.0.is_empty() {
+                if !sim.can_recycle() || recycled {
+                    return true;
+                }
+                recycled = true;
+            }
+            sim.deal();
+        }
+    }
+
+    // every tableau card face-up, so nothing is left to uncover
+    pub fn all_revealed(&self) -> bool {
+        self.rows.iter().all(|c| c.0.iter().all(|card| !card.hidden))
+    }
+
+    fn cards(&self) -> impl Iterator<Item = &Card> {
+        self.rows.iter().map(|c| &c.0)
+            .chain([&self.stock.0, &self.discard.0])
+            .chain(self.suit_piles.iter().map(|p| &p.0))
+            .flatten()
+    }
+
+    // every card exactly once, which a board read back from a file has to be checked for
+    pub fn is_complete(&self) -> bool {
+        let mut seen = 0u64;
+        self.cards().all(|card| {
+            let bit = 1 << (card.number * 4 + card.suit);
+            let fresh = seen & bit == 0;
+            seen |= bit;
+            fresh
+        }) && seen.count_ones() == 52
+    }
+
+    // every card exactly once across all piles
+    pub fn assert_invariants(&self) {
+        let mut seen = 0u64;
+        let mut count = 0;
+        for card in self.cards() {
+            let bit = 1 << (card.number * 4 + card.suit);
+            assert!(seen & bit == 0, "duplicate card {}", card.face());
+            seen |= bit;
+            count += 1;
+        }
+        assert_eq!(count, 52, "cards went missing");
+    }
+
+    pub fn check_win(&self) -> bool {
+        self.suit_piles.iter().map(|p| p.0.len()).sum::<usize>() == 52
+    }
+
+    pub fn to_ascii_board(&self) -> String {
+        let top = |pile: &Pile| pile.0.last().map_or("--".to_string(), |c| c.face());
+        let mut out = format!(
+            "Stock: {}  Discard: {}\nFoundations: {}\n\n",
+            self.stock.0.len(),
+            top(&self.discard),
+            self.suit_piles.iter().map(top).collect::<Vec<_>>().join(" ")
+        );
+
+        let height = self.rows.iter().map(|c| c.0.len()).max().unwrap_or(0);
+        for y in 0..height {
+            let mut line = String::new();
+            for col in &self.rows {
+                let cell = match col.0.get(y) {
+                    Some(card) if card.hidden => "##".to_string(),
+                    Some(card) => card.face(),
+                    None => String::new(),
+                };
+                line += &format!("{cell:<4}");
+            }
+            out += line.trim_end();
+            out.push('\n');
+        }
+        out
+    }
+
+    // key=value lines in the style of the stats file, a pile per line;
+    // the undo history is left out, so a loaded game starts without one
+    pub fn to_save(&self) -> String {
+        let pile = |cards: &[Card]| cards.iter().map(Card::token).collect::<Vec<_>>().join(" ");
+        let mut text = format!(
+            "seed={}\ndraw_count={}\nmoves={}\nscore={}\n",
+            self.seed, self.draw_count, self.moves, self.score
+        );
+        if let Some(n) = self.redeals_remaining {
+            text += &format!("redeals={n}\n");
+        }
+        if self.scoring == ScoringMode::Vegas {
+            text += "scoring=vegas\n";
+        }
+        text += &format!("stock={}\ndiscard={}\n", pile(&self.stock.0), pile(&self.discard.0));
+        for (i, col) in self.rows.iter().enumerate() {
+            text += &format!("column{i}={}\n", pile(&col.0));
+        }
+        for (i, found) in self.suit_piles.iter().enumerate() {
+            text += &format!("foundation{i}={}\n", pile(&found.0));
+        }
+        text
+    }
+
+    // unknown keys are skipped like in the stats file, but a bad card or an
+    // incomplete deck rejects the whole board
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut game = Game::new(0, 1);
+        for pile in game.rows.iter_mut().map(|c| &mut c.0)
+            .chain([&mut game.stock.0, &mut game.discard.0])
+            .chain(game.suit_piles.iter_mut().map(|p| &mut p.0))
+        {
+            pile.clear();
+        }
+
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            let cards = || value.split_whitespace().map(Card::parse_token).collect::<Option<Vec<_>>>();
+            let index = |prefix: &str, len: usize| key.strip_prefix(prefix)?.parse().ok().filter(|&i: &usize| i < len);
+            match key.trim() {
+                "seed" => game.seed = value.parse().ok()?,
+                "draw_count" => game.draw_count = value.parse().ok().filter(|n| [1, 3].contains(n))?,
+                "moves" => game.moves = value.parse().ok()?,
+                "score" => game.score = value.parse().ok()?,
+                "redeals" => game.redeals_remaining = Some(value.parse().ok()?),
+                "scoring" => game.scoring = if value == "vegas" {ScoringMode::Vegas} else {ScoringMode::Standard},
+                "stock" => game.stock.0 = cards()?,
+                "discard" => game.discard.0 = cards()?,
+                _ => {
+                    if let Some(i) = index("column", 7) {
+                        game.rows[i].0 = cards()?;
+                    } else if let Some(i) = index("foundation", 4) {
+                        game.suit_piles[i].0 = cards()?;
+                    }
+                }
+            }
+        }
+        game.is_complete().then_some(game)
+    }
+
+    // selection is not part of the board, so only cards and hidden flags are hashed
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        let piles = self.rows.iter().map(|c| &c.0)
+            .chain([&self.stock.0, &self.discard.0])
+            .chain(self.suit_piles.iter().map(|p| &p.0));
+        for pile in piles {
+            pile.len().hash(&mut hasher);
+            for card in pile {
+                (card.suit, card.number, card.hidden).hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+}
+
+// boards laid out card by card, shared by the tests of every module
+#[cfg(test)]
+pub mod fixtures {
+    use super::*;
+
+    // suits in SUITS order: 0 ♠, 1 ♥, 2 ♣, 3 ♦
+    pub fn card(number: u8, suit: u8) -> Card {
+        Card { suit, number, hidden: false, selected: false }
+    }
+
+    pub fn face_down(number: u8, suit: u8) -> Card {
+        Card { hidden: true, ..card(number, suit) }
+    }
+
+    // number and suit of each card, bottom of the pile first
+    pub fn faces(cards: &[Card]) -> Vec<(u8, u8)> {
+        cards.iter().map(|c| (c.number, c.suit)).collect()
+    }
+
+    // every pile emptied, to lay out positions by hand
+    pub fn clear_piles(game: &mut Game) {
+        for col in &mut game.rows {
+            col.0.clear();
+        }
+        game.stock.0.clear();
+        game.discard.0.clear();
+        for pile in &mut game.suit_piles {
+            pile.0.clear();
+        }
+    }
+
+    pub fn bare_game() -> Game {
+        let mut game = Game::new(1, 1);
+        clear_piles(&mut game);
+        game
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::fixtures::*;
+
+    #[test]
+    fn state_hash_comes_back_after_a_move_and_back() {
+        let mut game = bare_game();
+        game.rows[0].0 = vec![card(12, 0), card(11, 1)];
+        game.rows[1].0 = vec![card(12, 2)];
+        let start = game.state_hash();
+
+        game.apply(SelectedPos::Column(0, 1), SelectedPos::Column(1, 0)).unwrap();
+        assert_ne!(game.state_hash(), start);
+        game.apply(SelectedPos::Column(1, 1), SelectedPos::Column(0, 0)).unwrap();
+        assert_eq!(game.state_hash(), start);
+    }
+
+    #[test]
+    fn state_hash_changes_with_one_card() {
+        let mut game = bare_game();
+        game.rows[0].0 = vec![card(12, 0), card(11, 1)];
+        let start = game.state_hash();
+
+        game.rows[0].0[1] = card(11, 3);
+        assert_ne!(game.state_hash(), start);
+        game.rows[0].0[1] = card(11, 1);
+        game.rows[0].0[1].hidden = true;
+        assert_ne!(game.state_hash(), start);
+    }
+
+    // the discard's top card onto column 0
+    fn discard_onto(column: Vec<Card>, top: Card) -> (Game, Result<Move, MoveError>) {
+        let mut game = bare_game();
+        game.rows[0].0 = column;
+        game.discard.0 = vec![top];
+        let result = game.apply(SelectedPos::Discard, SelectedPos::Column(0, 0));
+        (game, result)
+    }
+
+    #[test]
+    fn discard_king_onto_empty_column() {
+        let (game, result) = discard_onto(vec![], card(12, 0));
+        assert_eq!(result, Ok(Move::DiscardToColumn(0)));
+        assert!(game.discard.0.is_empty());
+        assert_eq!(faces(&game.rows[0].0), faces(&[card(12, 0)]));
+    }
+
+    #[test]
+    fn discard_queen_onto_empty_column_is_refused() {
+        let (game, result) = discard_onto(vec![], card(11, 1));
+        assert_eq!(result, Err(MoveError::ColumnMismatch));
+        assert_eq!(faces(&game.discard.0), faces(&[card(11, 1)]));
+        assert!(game.rows[0].0.is_empty());
+    }
+
+    #[test]
+    fn discard_red_queen_onto_black_king() {
+        let (game, result) = discard_onto(vec![card(12, 0)], card(11, 1));
+        assert_eq!(result, Ok(Move::DiscardToColumn(0)));
+        assert_eq!(faces(&game.rows[0].0), faces(&[card(12, 0), card(11, 1)]));
+    }
+
+    #[test]
+    fn discard_red_queen_onto_red_king_is_refused() {
+        let (game, result) = discard_onto(vec![card(12, 1)], card(11, 1));
+        assert_eq!(result, Err(MoveError::ColumnMismatch));
+        assert_eq!(faces(&game.rows[0].0), faces(&[card(12, 1)]));
+    }
+
+    #[test]
+    fn undo_stack_stays_within_its_limit() {
+        let mut game = bare_game();
+        game.undo_limit = 3;
+        game.rows[0].0 = vec![card(12, 0), card(11, 1)];
+        game.rows[1].0 = vec![card(12, 2)];
+        for i in 0..10 {
+            let (from, to) = if i % 2 == 0 {(0, 1)} else {(1, 0)};
+            game.apply(SelectedPos::Column(from, 1), SelectedPos::Column(to, 0)).unwrap();
+            assert!(game.undo_stack.len() <= 3);
+        }
+        // ten moves left the queen on the first column, the last three go back in turn
+        assert_eq!(faces(&game.rows[0].0), faces(&[card(12, 0), card(11, 1)]));
+        game.undo();
+        assert_eq!(faces(&game.rows[1].0), faces(&[card(12, 2), card(11, 1)]));
+        game.undo();
+        game.undo();
+        assert_eq!(faces(&game.rows[1].0), faces(&[card(12, 2), card(11, 1)]));
+        assert!(game.undo_stack.is_empty());
+        game.undo();
+        assert_eq!(faces(&game.rows[1].0), faces(&[card(12, 2), card(11, 1)]));
+    }
+
+    #[test]
+    fn standard_score_over_a_scripted_game() {
+        let mut game = bare_game();
+        game.rows[0].0 = vec![face_down(5, 2), card(0, 0)];
+        game.rows[1].0 = vec![card(12, 1)];
+        game.rows[2].0 = vec![card(1, 3)];
+        game.discard.0 = vec![card(11, 0)];
+
+        // A♠ up, turning the 6♣ over: +10 +5
+        game.apply(SelectedPos::Column(0, 1), SelectedPos::SuitPile(0)).unwrap();
+        assert_eq!(game.score, 15);
+        // Q♠ from the discard onto K♥: +5
+        game.apply(SelectedPos::Discard, SelectedPos::Column(1, 0)).unwrap();
+        assert_eq!(game.score, 20);
+        // A♠ back down onto 2♦: -15
+        game.apply(SelectedPos::SuitPile(0), SelectedPos::Column(2, 0)).unwrap();
+        assert_eq!(game.score, 5);
+        // a refused move scores nothing
+        assert!(game.apply(SelectedPos::Column(0, 0), SelectedPos::Column(1, 1)).is_err());
+        assert_eq!(game.score, 5);
+    }
+
+    #[test]
+    fn a_two_cannot_start_a_foundation() {
+        let game = bare_game();
+        for n in 0..4 {
+            assert!(!game.validate_suit(n, &card(1, n as u8)));
+        }
+    }
+
+    #[test]
+    fn empty_foundation_takes_only_its_own_ace() {
+        let game = bare_game();
+        for n in 0..4 {
+            for suit in 0..4 {
+                assert_eq!(game.validate_suit(n, &card(0, suit)), suit == n as u8);
+            }
+        }
+        let mut game = bare_game();
+        game.rows[0].0 = vec![card(0, 1)];
+        assert_eq!(game.apply(SelectedPos::Column(0, 0), SelectedPos::SuitPile(0)), Err(MoveError::FoundationMismatch));
+        let up = Move::ColumnToFoundation { from: 0, to: 1, revealed: false };
+        assert_eq!(game.apply(SelectedPos::Column(0, 0), SelectedPos::SuitPile(1)), Ok(up));
+    }
+
+    #[test]
+    fn foundation_climbs_one_rank_at_a_time() {
+        let mut game = bare_game();
+        game.discard.0 = vec![card(2, 0), card(1, 0), card(0, 0)];
+        for _ in 0..3 {
+            assert_eq!(game.apply(SelectedPos::Discard, SelectedPos::SuitPile(0)), Ok(Move::DiscardToFoundation(0)));
+        }
+        assert_eq!(faces(&game.suit_piles[0].0), [(0, 0), (1, 0), (2, 0)]);
+
+        // A then 3
+        let mut game = bare_game();
+        game.suit_piles[0].0 = vec![card(0, 0)];
+        game.discard.0 = vec![card(2, 0)];
+        assert_eq!(game.apply(SelectedPos::Discard, SelectedPos::SuitPile(0)), Err(MoveError::FoundationMismatch));
+
+        // 2 on 2
+        game.suit_piles[0].0.push(card(1, 0));
+        game.discard.0 = vec![card(1, 0)];
+        assert_eq!(game.apply(SelectedPos::Discard, SelectedPos::SuitPile(0)), Err(MoveError::FoundationMismatch));
+        assert_eq!(game.suit_piles[0].0.len(), 2);
+    }
+
+    #[test]
+    fn is_valid_run_needs_alternating_descending_face_up_cards() {
+        assert!(is_valid_run(&[card(12, 0), card(11, 1), card(10, 2)]));
+        assert!(is_valid_run(&[card(4, 3)]));
+        // K♠ Q♣ J♥ doesn't alternate
+        assert!(!is_valid_run(&[card(12, 0), card(11, 2), card(10, 1)]));
+        // a rank skipped, and a rank climbing
+        assert!(!is_valid_run(&[card(12, 0), card(10, 1)]));
+        assert!(!is_valid_run(&[card(10, 0), card(11, 1)]));
+        assert!(!is_valid_run(&[card(12, 0), face_down(11, 1)]));
+    }
+
+    #[test]
+    fn broken_runs_cannot_move() {
+        let mut game = bare_game();
+        // K♠ Q♣ J♥ doesn't alternate, K♦ J♠ skips the queen; both would start an empty column
+        game.rows[0].0 = vec![card(12, 0), card(11, 2), card(10, 1)];
+        game.rows[1].0 = vec![card(12, 3), card(10, 0)];
+        assert_eq!(game.apply(SelectedPos::Column(0, 0), SelectedPos::Column(2, 0)), Err(MoveError::BrokenRun));
+        assert_eq!(game.apply(SelectedPos::Column(1, 0), SelectedPos::Column(3, 0)), Err(MoveError::BrokenRun));
+        assert_eq!(game.rows[0].0.len(), 3);
+        assert_eq!(game.rows[1].0.len(), 2);
+    }
+
+    #[test]
+    fn save_round_trips_the_board() {
+        let mut game = Game::new(3, 3);
+        game.set_scoring(ScoringMode::Vegas);
+        game.redeals_remaining = Some(2);
+        game.deal();
+        if let Some((src, dest)) = game.legal_moves().first().copied() {
+            game.apply(src, dest).unwrap();
+        }
+        game.rows[6].0[6].selected = true;
+
+        let loaded = Game::parse(&game.to_save()).unwrap();
+        let piles = |g: &Game| -> Vec<Vec<String>> {
+            g.rows.iter().map(|c| &c.0)
+                .chain([&g.stock.0, &g.discard.0])
+                .chain(g.suit_piles.iter().map(|p| &p.0))
+                .map(|pile| pile.iter().map(Card::token).collect())
+                .collect()
+        };
+        assert_eq!(piles(&loaded), piles(&game));
+        assert!(loaded.rows[6].0[6].selected);
+        assert!(loaded.rows[6].0[0].hidden);
+        assert_eq!(
+            (loaded.seed, loaded.draw_count, loaded.moves, loaded.score, loaded.redeals_remaining, loaded.scoring),
+            (game.seed, game.draw_count, game.moves, game.score, game.redeals_remaining, game.scoring)
+        );
+    }
+
+    #[test]
+    fn card_displays_rank_and_suit_unless_hidden() {
+        for (card, shown) in [(card(0, 0), "A♠"), (card(9, 1), "10♥"), (card(12, 3), "K♦")] {
+            assert_eq!(format!("{card}"), shown);
+        }
+        assert_eq!(format!("{}", face_down(12, 3)), "");
+    }
+
+    #[test]
+    fn shuffled_deck_holds_each_card_once() {
+        for seed in 0..20 {
+            let deck = Deck::shuffled(&mut StdRng::seed_from_u64(seed));
+            assert_eq!(deck.len(), 52);
+            let mut seen = [[false; 13]; 4];
+            for card in &deck.0 {
+                assert!(card.hidden);
+                assert!(!std::mem::replace(&mut seen[card.suit as usize][card.number as usize], true));
+            }
+        }
+    }
+
+    #[test]
+    fn only_face_up_cards_are_selectable() {
+        let mut game = bare_game();
+        game.rows[0].0 = vec![face_down(3, 0), face_down(7, 1), card(9, 2)];
+        for y in 0..2 {
+            assert_eq!(game.selectable(SelectedPos::Column(0, y)), SelectedPos::None);
+        }
+        assert_eq!(game.selectable(SelectedPos::Column(0, 2)), SelectedPos::Column(0, 2));
+        assert_eq!(game.selectable(SelectedPos::Column(0, 3)), SelectedPos::None);
+        // a column emptied by play is somewhere to put a king, never a card to pick up
+        assert_eq!(game.selectable(SelectedPos::Column(1, 0)), SelectedPos::None);
+        assert_eq!(game.apply(SelectedPos::Column(1, 0), SelectedPos::Column(2, 0)), Err(MoveError::EmptySource));
+    }
+
+    #[test]
+    fn only_the_bottom_card_goes_up() {
+        let mut game = bare_game();
+        game.rows[0].0 = vec![card(1, 3), card(0, 0)];
+        game.rows[1].0 = vec![card(0, 1), card(12, 2)];
+
+        assert!(game.is_bottom_card(0, 1));
+        assert!(!game.is_bottom_card(1, 0));
+        assert_eq!(game.apply(SelectedPos::Column(1, 0), SelectedPos::SuitPile(1)), Err(MoveError::NotTopCard));
+        assert_eq!(game.rows[1].0.len(), 2);
+        let up = Move::ColumnToFoundation { from: 0, to: 0, revealed: false };
+        assert_eq!(game.apply(SelectedPos::Column(0, 1), SelectedPos::SuitPile(0)), Ok(up));
+        assert_eq!(faces(&game.suit_piles[0].0), [(0, 0)]);
+    }
+
+    #[test]
+    fn branch_comes_back_to_the_saved_line() {
+        let mut game = bare_game();
+        game.rows[0].0 = vec![card(12, 0), card(11, 1)];
+        game.rows[1].0 = vec![card(12, 2)];
+        game.rows[2].0 = vec![card(0, 0)];
+        let start = game.state_hash();
+        assert!(!game.return_to_branch());
+
+        // the queen across, then the ace up, saved as the line to keep
+        game.apply(SelectedPos::Column(0, 1), SelectedPos::Column(1, 0)).unwrap();
+        game.apply(SelectedPos::Column(2, 0), SelectedPos::SuitPile(0)).unwrap();
+        let line = game.state_hash();
+        game.save_branch();
+
+        // back before both moves, and the queen goes home instead
+        game.undo();
+        game.undo();
+        game.apply(SelectedPos::Column(2, 0), SelectedPos::SuitPile(0)).unwrap();
+        assert_ne!(game.state_hash(), line);
+
+        assert!(game.return_to_branch());
+        assert_eq!(game.state_hash(), line);
+        assert_eq!((game.moves, game.undo_stack.len()), (2, 2));
+        // the saved line keeps its own history
+        game.undo();
+        game.undo();
+        assert_eq!(game.state_hash(), start);
+        // and can be gone back to again
+        assert!(game.return_to_branch());
+        assert_eq!(game.state_hash(), line);
+    }
+
+    #[test]
+    fn a_card_left_face_down_turns_over_once() {
+        let up = Move::ColumnToFoundation { from: 0, to: 0, revealed: true };
+        let across = Move::ColumnToColumn { from: 0, to: 1, count: 1, revealed: true };
+        for (dest, recorded) in [(SelectedPos::SuitPile(0), up), (SelectedPos::Column(1, 0), across)] {
+            let mut game = bare_game();
+            // the A♠ can go up, the Q♥ across onto the K♠, either way the 6♣ is left on its own
+            let top = if dest == SelectedPos::SuitPile(0) {card(0, 0)} else {card(11, 1)};
+            game.rows[0].0 = vec![face_down(5, 2), top];
+            game.rows[1].0 = vec![card(12, 0)];
+            let start = game.score;
+            let reveals = |game: &Game| game.undo_stack.iter().flatten()
+                .filter(|mv| matches!(mv, Move::ColumnToFoundation { revealed: true, .. } | Move::ColumnToColumn { revealed: true, .. }))
+                .count();
+
+            assert_eq!(game.apply(SelectedPos::Column(0, 1), dest), Ok(recorded));
+            assert!(!game.rows[0].0[0].hidden);
+            assert_eq!(reveals(&game), 1);
+            let score = game.score;
+
+            game.undo();
+            assert_eq!(faces(&game.rows[0].0), faces(&[card(5, 2), top]));
+            assert!(game.rows[0].0[0].hidden);
+            assert_eq!(game.score, start);
+
+            // played again, it turns over and scores just as the first time
+            assert_eq!(game.apply(SelectedPos::Column(0, 1), dest), Ok(recorded));
+            assert!(!game.rows[0].0[0].hidden);
+            assert_eq!(reveals(&game), 1);
+            assert_eq!(game.score, score);
+        }
+    }
+}
@@ -1,120 +1,67 @@
-use std::io;
+mod engine;
+mod freecell;
+mod game;
+mod spider;
 
-use crossterm::{event::{self, EnableMouseCapture, Event, KeyCode, MouseEventKind}, execute};
-use rand::{seq::SliceRandom, thread_rng};
-use ratatui::{buffer::Buffer, layout::Rect, style::{Style, Stylize}, symbols::{self, border}, text::{Span, ToSpan}, widgets::{Block, Borders, Paragraph, Widget}, DefaultTerminal, Frame};
+use std::{fmt, fs, io, path::{Path, PathBuf}, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
 
-#[derive(Debug, Clone, Copy)]
-struct Card {
-    suit: u8,
-    number: u8,
-    hidden: bool,
-    selected: bool
-}
-
-impl Card {
-    const NUMBERS: [&'static str; 13] = [
-        "A",
-        "2",
-        "3",
-        "4",
-        "5",
-        "6",
-        "7",
-        "8",
-        "9",
-        "10",
-        "J",
-        "Q",
-        "K",
-    ];
-
-    const SUITS: [&'static str; 4] = [
-        "♠",
-        "♥",
-        "♣",
-        "♦",
-    ];
-
-    const DECK: [Self; 52] = {
-        let mut d = [const { Card {
-            suit: 0,
-            number: 0,
-            hidden: true,
-            selected: false
-        } }; 52];
-        let mut i = 0;
-        while i < 52 {
-            d[i].number = i as u8 / 4;
-            d[i].suit = i as u8 % 4;
-            i += 1;
-        }
-        d
-    };
-
-    fn color(&self) -> u8 {
-        self.suit % 2
-    }
-}
-
-impl ToString for Card {
-    fn to_string(&self) -> String {
-        if self.hidden {
-            return String::new();
-        }
-        format!(
-            "{}{}",
-            Card::NUMBERS[self.number as usize],
-            Card::SUITS[self.suit as usize]
-        )
-    }
-}
+use crossterm::{event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseEventKind}, execute};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use ratatui::{buffer::Buffer, layout::{Alignment, Position, Rect}, style::{Color, Style, Stylize}, symbols::{self, border}, text::Span, widgets::{Block, Borders, Clear, Padding, Paragraph, Widget}, DefaultTerminal, Frame};
 
-impl ToSpan for Card {
-    fn to_span(&self) -> Span<'_> {
-        Span::styled(
-            self.to_string()
-            , match (self.color() != 0, self.selected) {
-                (true, true) => Style::new().red().on_white(),
-                (true, false) => Style::new().red(),
-                (false, true) => Style::new().black().on_white(),
-                (false, false) => Style::new().white()
-            }
-        )
-    }
-}
+use engine::Engine;
+use freecell::{FreeCell, Spot};
+use spider::Spider;
+use game::{is_valid_run, Card, Column, Game, MoveError, Pile, ScoringMode, SelectedPos, UNDO_LIMIT};
 
+// card outlines, corners and color taken from the theme; the card itself belongs to the game module
 impl Card {
-    const BLOCK_SINGLE: Block<'static> = {
-       Block::bordered().border_set(border::ROUNDED)
-    };
+    fn block_single(theme: &Theme) -> Block<'static> {
+       Block::bordered().border_set(theme.border()).border_style(theme.card_border)
+    }
 
-    const BLOCK_FIRST: Block<'static> = {
+    fn block_first(theme: &Theme) -> Block<'static> {
         Block::bordered()
-            .border_set(border::ROUNDED)
+            .border_set(theme.border())
+            .border_style(theme.card_border)
             .borders(Borders::TOP.union(Borders::LEFT).union(Borders::RIGHT))
-    };
+    }
 
-    const BLOCK_MIDDLE: Block<'static> = {
+    fn block_middle(theme: &Theme) -> Block<'static> {
         Block::bordered()
             .border_set(symbols::border::Set {
                 bottom_left: symbols::line::ROUNDED.vertical_right,
                 bottom_right: symbols::line::ROUNDED.vertical_left,
                 top_left: symbols::line::ROUNDED.vertical_right,
                 top_right: symbols::line::ROUNDED.vertical_left,
-                ..symbols::border::ROUNDED
+                ..theme.border()
             })
+            .border_style(theme.card_border)
             .borders(Borders::TOP.union(Borders::LEFT).union(Borders::RIGHT))
-    };
+    }
 
-    const BLOCK_LAST: Block<'static> = {
+    fn block_last(theme: &Theme) -> Block<'static> {
         Block::bordered()
             .border_set(symbols::border::Set {
                 top_left: symbols::line::ROUNDED.vertical_right,
                 top_right: symbols::line::ROUNDED.vertical_left,
-                ..symbols::border::ROUNDED
+                ..theme.border()
             })
-    };
+            .border_style(theme.card_border)
+    }
+
+    // an empty column, dashed so it doesn't pass for a foundation or a face-down card
+    fn block_placeholder(theme: &Theme) -> Block<'static> {
+        Block::bordered()
+            .border_set(symbols::border::Set {
+                vertical_left: "╎",
+                vertical_right: "╎",
+                horizontal_top: "╌",
+                horizontal_bottom: "╌",
+                ..theme.border()
+            })
+            .border_style(theme.empty.dim())
+    }
 
     const BLOCK_EMPTY: Block<'static> = {
         Block::bordered()
@@ -122,353 +69,3968 @@ impl Card {
     };
 }
 
-struct App {
-    rows: [Column; 7],
-    stock: Pile,
-    discard: Pile,
-    suit_piles: [Pile; 4],
-    selected_pos: SelectedPos,
-    exit: bool,
+// covered cards show their top border and label, so the fan step stays fixed
+const CARD_STEP: u16 = 2;
+// columns between the fanned discard cards in draw-three
+const FAN_STEP: u16 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CardSize {
+    width: u16,
+    height: u16,
 }
 
-#[derive(PartialEq, Debug, Clone, Copy)]
-enum SelectedPos {
-    None,
-    Discard,
-    SuitPile(usize),
-    Column(usize, usize)
+impl CardSize {
+    // narrowest card that still fits "10♠" between its borders
+    const MIN: Self = Self { width: 5, height: 3 };
+    // room for three rows of up to four pips between the label and the corner
+    const LARGE: Self = Self { width: 9, height: 7 };
+
+    fn parse(s: &str) -> Option<Self> {
+        let (w, h) = s.split_once('x')?;
+        let size = Self { width: w.parse().ok()?, height: h.parse().ok()? };
+        (size.width >= Self::MIN.width && size.height >= Self::MIN.height).then_some(size)
+    }
+
+    // x of the stock/discard/foundation column, right of the tableau and a one-cell gap
+    fn piles_x(&self) -> u16 {
+        7 * self.width + 1
+    }
+
+    fn board_width(&self) -> u16 {
+        self.piles_x() + self.width
+    }
+
+    // the right-hand piles are six cards tall
+    fn board_height(&self) -> u16 {
+        6 * self.height + 1
+    }
 }
 
-impl App {
-    fn init() -> Self {
-        let mut res = Self {
-            rows: [const { Column(Vec::new()) }; 7],
-            stock: Pile(Vec::new()),
-            discard: Pile(Vec::new()),
-            suit_piles: [const { Pile(Vec::new()) }; 4],
-            selected_pos: SelectedPos::None,
-            exit: false
-        };
+impl Default for CardSize {
+    fn default() -> Self {
+        Self { width: 5, height: 5 }
+    }
+}
 
-        let mut rng = thread_rng();
-        
-        let mut deck = Card::DECK.choose_multiple(&mut rng, 52).map(|c| *c);
+struct Theme {
+    // fills the board cells no pile is drawn over
+    background: char,
+    background_style: Style,
+    red: Style,
+    black: Style,
+    // laid over a selected card's colors
+    selected: Style,
+    card_border: Style,
+    // fills the face of a face-down card, so a stack of them doesn't read as an empty pile
+    card_back: char,
+    back_style: Style,
+    // the placeholder outline of an empty pile
+    empty: Style,
+    separator: Style,
+    focus: Style,
+    hint: Style,
+    // outlines where the selection can go
+    target: Style,
+    // outlines where a move was just refused
+    refused: Style,
+    light: bool,
+    frame: bool,
+    frame_style: Style,
+    // where the rank and suit sit on the card face
+    align: Alignment,
+    card_glyphs: bool,
+    // letters for suits and square corners, for terminals without the Unicode glyphs
+    ascii: bool,
+    // a color per suit rather than per red/black
+    four_color: bool,
+}
 
-        for i in 0..7 {
-            res.rows[i] = Column(deck.by_ref().take(i + 1).collect());
-            res.rows[i].0[i].hidden = false;
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    fn dark() -> Self {
+        Self {
+            background: ' ',
+            background_style: Style::new(),
+            red: Style::new().red(),
+            black: Style::new().white(),
+            selected: Style::new().on_dark_gray(),
+            card_border: Style::new(),
+            card_back: '▚',
+            back_style: Style::new().blue(),
+            empty: Style::new(),
+            separator: Style::new().dark_gray(),
+            focus: Style::new().yellow(),
+            hint: Style::new().black().on_green(),
+            target: Style::new().green(),
+            refused: Style::new().red(),
+            light: false,
+            frame: false,
+            frame_style: Style::new(),
+            align: Alignment::Left,
+            card_glyphs: false,
+            ascii: false,
+            four_color: false,
         }
+    }
 
-        res.stock = Pile(deck.collect());
+    // for terminals with a white background, where white cards would vanish
+    fn light() -> Self {
+        Self {
+            black: Style::new().black(),
+            selected: Style::new().on_gray(),
+            empty: Style::new().gray(),
+            separator: Style::new().gray(),
+            focus: Style::new().blue(),
+            light: true,
+            ..Self::dark()
+        }
+    }
 
-        res
+    // swaps between dark and light colors, leaving the layout options alone
+    fn toggle_light(&mut self) {
+        let other = if self.light {Self::dark()} else {Self::light()};
+        self.background_style = other.background_style;
+        self.red = other.red;
+        self.black = other.black;
+        self.selected = other.selected;
+        self.card_border = other.card_border;
+        self.back_style = other.back_style;
+        self.empty = other.empty;
+        self.separator = other.separator;
+        self.focus = other.focus;
+        self.hint = other.hint;
+        self.target = other.target;
+        self.refused = other.refused;
+        self.light = other.light;
     }
 
-    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
-        while !self.exit {
-            terminal.draw(|frame| self.draw(frame))?;
-            self.handle_events()?
-        }
-        Ok(())
+    // cells between the terminal edge and the board
+    fn margin(&self) -> u16 {
+        if self.frame {1} else {0}
     }
-    
-    fn draw(&self, frame: &mut Frame) {
-        frame.render_widget(self, frame.area());
+
+    fn border(&self) -> border::Set {
+        if self.ascii {border::PLAIN} else {border::ROUNDED}
     }
 
-    fn handle_events(&mut self) -> io::Result<()> {
-        let ev = event::read()?;
-        match ev {
-            Event::Key(ev) => {
-                match ev.code {
-                    KeyCode::Esc => {self.exit = true}
-                    KeyCode::Char('c') => {self.selected_pos = SelectedPos::None}
-                    KeyCode::Char('d') => {
-                        if let Some(mut card) = self.stock.0.pop() {
-                            card.hidden = false;
-                            self.discard.0.push(card);
-                        }
-                    }
-                    _ => {}
-                }
-            }
-            Event::Mouse(ev) => {
-                if ev.kind != MouseEventKind::Up(event::MouseButton::Left) {
-                    return Ok(());
-                }
+    fn card_span<'a>(&self, card: &'a Card) -> Span<'a> {
+        if self.ascii && !card.hidden {
+            return Span::styled(card.ascii_face(), self.card_style(card));
+        }
+        if self.card_glyphs && !card.hidden {
+            return Span::styled(card.glyph().to_string(), self.card_style(card));
+        }
+        Span::styled(card.to_string(), self.card_style(card))
+    }
 
-                let new_pos = self.get_selected_pos(ev.column as usize, ev.row as usize);
-                
-                self.handle_move(new_pos);
-                if self.check_win() {
-                    self.exit = true;
-                }
-                self.selected_pos = new_pos;
-            }
-            _ => {}
+    // face-up rendering of a card that hasn't been dealt yet
+    fn preview_span(&self, card: &Card) -> Span<'static> {
+        Span::styled(card.face(), self.card_style(card).dim().italic())
+    }
+
+    // the four-color deck keeps spades and hearts as they are, and makes clubs green and diamonds blue
+    fn card_style(&self, card: &Card) -> Style {
+        let mut style = if card.color() != 0 {self.red} else {self.black};
+        if self.four_color {
+            style = match card.suit {
+                2 => style.green(),
+                3 => style.blue(),
+                _ => style,
+            };
         }
-        Ok(())
+        if card.selected {
+            style = style.patch(self.selected);
+        }
+        style
     }
+}
 
-    fn get_selected_pos(&mut self, x: usize, y: usize) -> SelectedPos {
-        match x {
-            0..=34 => {
-                let x = x as usize / 5;
-                let col = &self.rows[x];
-                let y = y as usize / 2;
-                if col.0.len() == 0 {
-                    return SelectedPos::Column(x, 0)
-                }
-                if y >= col.0.len() {
-                    let y = col.0.len() - 1;
-                    return SelectedPos::Column(x, y)
-                }
-                if col.0[y].hidden {
-                    return SelectedPos::Column(x, 0)
-                }
-                SelectedPos::Column(x, y)
-            }
-            36..41 => {
-                match y {
-                    0..5 => {
-                        if let Some(mut card) = self.stock.0.pop() {
-                            card.hidden = false;
-                            self.discard.0.push(card);
-                        } else {
-                            if self.discard.0.len() == 0 {
-                                return SelectedPos::None;
-                            }
-                            self.stock.0.extend(self.discard.0.drain(1..).rev());
-                            for c in &mut self.stock.0 {
-                                c.hidden = true;
-                            }
-                        }
-                        SelectedPos::Discard
-                    }
-                    5..10 => {
-                        if self.discard.0.len() == 0 {
-                            return SelectedPos::None
-                        }
-                        SelectedPos::Discard
-                    }
-                    10..30 => {
-                        SelectedPos::SuitPile(y / 5 - 2)
-                    }
-                    _ => {
-                        SelectedPos::None
-                    }
-                }
-            }
-            _ => {SelectedPos::None}
+#[derive(Debug, Default)]
+struct Stats {
+    played: u32,
+    won: u32,
+    // seconds
+    best_time: Option<u64>,
+    // only won games set it
+    best_score: Option<i32>,
+    total_moves: u64,
+    streak: u32,
+    best_streak: u32,
+}
+
+// today's UTC date as YYYYMMDD, so everyone playing the daily deal on a day gets the same one
+fn daily_seed() -> u64 {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    // days since 0000-03-01, counted in 400-year eras so leap days fall at the end of each year
+    let days = secs / 86400 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 {month + 3} else {month - 9};
+    let year = era * 400 + year_of_era + if month <= 2 {1} else {0};
+    year * 10_000 + month * 100 + day
+}
+
+// what can end the run or spoil a save: the terminal or a file failing, or a save that won't parse
+#[derive(Debug)]
+enum Error {
+    Io(io::Error),
+    BadSave,
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => err.fmt(f),
+            Error::BadSave => f.write_str("not a saved game"),
         }
     }
+}
 
-    fn handle_move(&mut self, dest: SelectedPos) {
-        let src = &self.selected_pos;
+impl std::error::Error for Error {}
 
-        match dest {
-            SelectedPos::None | SelectedPos::Discard => {}
-            SelectedPos::SuitPile(n) => {
-                if src == &SelectedPos::Discard {
-                    let card = self.discard.0.last().unwrap();
-                    if !self.validate_suit(n, card) {
-                        return;
-                    }
-                    self.suit_piles[n].0.push(self.discard.0.pop().unwrap());
-                    return;
-                }
+// hands the terminal back in its normal state however the run ends, errors and panics included
+struct TerminalGuard;
 
-                if let SelectedPos::Column(x, y) = src {
-                    if self.rows[*x].0.len() == 0 || self.rows[*x].0.len() > *y + 1 {
-                        // only allow one card
-                        return;
-                    }
-                    if !self.validate_suit(n, &self.rows[*x].0[*y]) {
-                        return;
-                    }
-                    self.suit_piles[n].0.push(self.rows[*x].0.pop().unwrap());
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), DisableMouseCapture);
+        ratatui::restore();
+    }
+}
 
-                    if let Some(card) = self.rows[*x].0.last_mut() {
-                        card.hidden = false;
-                    }
-                    return;
-                }
-            }
-            SelectedPos::Column(x, _) => {
-                match src {
-                    SelectedPos::None => {},
-                    SelectedPos::Discard => {
-                        let card = self.discard.0.last().unwrap();
-                        if !self.validate_col(x, card) {
-                            return;
-                        }
-                        self.rows[x].0.push(self.discard.0.pop().unwrap());
-                        return;
-                    },
-                    SelectedPos::SuitPile(n) => {
-                        let card = match self.suit_piles[*n].0.last() {
-                            Some(card) => card,
-                            None => return
-                        };
-                        if !self.validate_col(x, card) {
-                            return;
-                        }
-                        self.rows[x].0.push(self.suit_piles[*n].0.pop().unwrap());
-                        return;
-                    },
-                    SelectedPos::Column(sx, sy) => {
-                        if *sx == x {
-                            return;
-                        }
-                        if self.rows[*sx].0.len() == 0 {
-                            return;
-                        }
-                        let card = &self.rows[*sx].0[*sy];
-                        if !self.validate_col(x, card) {
-                            return;
-                        }
-                        let tmp: Vec<Card> = self.rows[*sx].0.drain(sy..).collect();
-                        self.rows[x].0.extend(tmp);
+// a dotfile in the user's home directory
+fn home_file(name: &str) -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(name))
+}
 
-                        if let Some(card) = self.rows[*sx].0.last_mut() {
-                            card.hidden = false;
-                        }
-                        return;
-                    },
-                }
-            },
+impl Stats {
+    fn path() -> Option<PathBuf> {
+        home_file(".solitui_stats")
+    }
+
+    // one key=value per line, unknown keys and bad values are skipped so
+    // older and newer versions can share the file
+    fn parse(text: &str) -> Self {
+        let mut stats = Self::default();
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "played" => stats.played = value.parse().unwrap_or(stats.played),
+                "won" => stats.won = value.parse().unwrap_or(stats.won),
+                "best_time" => stats.best_time = value.parse().ok(),
+                "best_score" => stats.best_score = value.parse().ok(),
+                "total_moves" => stats.total_moves = value.parse().unwrap_or(stats.total_moves),
+                "streak" => stats.streak = value.parse().unwrap_or(stats.streak),
+                "best_streak" => stats.best_streak = value.parse().unwrap_or(stats.best_streak),
+                _ => {}
+            }
         }
+        stats
     }
 
-    fn validate_suit(&self, pile_n: usize, card: &Card) -> bool {
-        if let Some(last) = self.suit_piles[pile_n].0.last() {
-            last.suit == card.suit &&
-            last.number + 1 == card.number
-        } else {
-            card.number == 0
+    fn load(path: &Path) -> Self {
+        fs::read_to_string(path).map(|text| Self::parse(&text)).unwrap_or_default()
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        let mut text = format!(
+            "played={}\nwon={}\ntotal_moves={}\nstreak={}\nbest_streak={}\n",
+            self.played, self.won, self.total_moves, self.streak, self.best_streak
+        );
+        if let Some(best) = self.best_time {
+            text += &format!("best_time={best}\n");
         }
+        if let Some(best) = self.best_score {
+            text += &format!("best_score={best}\n");
+        }
+        fs::write(path, text)
     }
 
-    fn validate_col(&self, col_n: usize, card: &Card) -> bool {
-        if let Some(last) = self.rows[col_n].0.last() {
-            last.color() != card.color() &&
-            last.number == card.number + 1
+    // score is None for games played unscored
+    fn record(&mut self, won: bool, secs: u64, moves: u32, score: Option<i32>) {
+        self.played += 1;
+        self.total_moves += moves as u64;
+        if won {
+            self.won += 1;
+            self.streak += 1;
+            self.best_streak = self.best_streak.max(self.streak);
+            self.best_time = Some(self.best_time.map_or(secs, |best| best.min(secs)));
+            self.best_score = self.best_score.max(score);
         } else {
-            card.number == 12 // King
+            self.streak = 0;
         }
     }
 
-    fn check_win(&self) -> bool {
-        self.suit_piles.iter().map(|p| p.0.len()).sum::<usize>() == 52
+    fn summary(&self) -> String {
+        let rate = (self.won * 100).checked_div(self.played).unwrap_or(0);
+        let avg = self.total_moves.checked_div(self.played as u64).unwrap_or(0);
+        let best = self.best_time.map_or("--:--".to_string(), |t| format!("{:02}:{:02}", t / 60, t % 60));
+        let best_score = self.best_score.map_or("--".to_string(), |s| s.to_string());
+        format!(
+            "Played      {}\nWon         {}\nWin rate    {}%\nBest time   {}\nBest score  {}\nAvg moves   {}\nBest streak {}",
+            self.played, self.won, rate, best, best_score, avg, self.best_streak
+        )
+    }
+}
+
+// the commands a player is likely to want on other keys
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Action {
+    Draw,
+    ClearSelection,
+    NewGame,
+    Undo,
+    Hint,
+    Quit,
+    Autocomplete,
+}
+
+impl Action {
+    // as written in the keys file
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Draw => "draw",
+            Action::ClearSelection => "clear_selection",
+            Action::NewGame => "new_game",
+            Action::Undo => "undo",
+            Action::Hint => "hint",
+            Action::Quit => "quit",
+            Action::Autocomplete => "autocomplete",
+        }
     }
 }
 
-struct Column(Vec<Card>);
+// which key triggers each action; keys not in here keep their fixed meaning
+#[derive(Debug)]
+struct KeyMap([(Action, KeyCode); 7]);
 
-struct Pile(Vec<Card>);
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self([
+            (Action::Draw, KeyCode::Char('d')),
+            (Action::ClearSelection, KeyCode::Char('c')),
+            (Action::NewGame, KeyCode::Char('n')),
+            (Action::Undo, KeyCode::Char('u')),
+            (Action::Hint, KeyCode::Char('H')),
+            (Action::Quit, KeyCode::Esc),
+            (Action::Autocomplete, KeyCode::Char('a')),
+        ])
+    }
+}
 
-impl Widget for &Column {
-    fn render(self, area: Rect, buf: &mut Buffer) {
-        if self.0.len() == 0 {return}
-        let x = area.x;
-        let mut y = area.y;
-        let first = &self.0[0];
-        if self.0.len() == 1 {
-            Paragraph::new(first.to_span())
-                .block(Card::BLOCK_SINGLE)
-                .render(Rect::new(x, y, 5, 5), buf);
-            return
+impl KeyMap {
+    fn path() -> Option<PathBuf> {
+        home_file(".solitui_keys")
+    }
+
+    // action=key per line, the key a single character or a name like esc or f5;
+    // anything unrecognised keeps the default
+    fn parse(text: &str) -> Self {
+        let mut map = Self::default();
+        for line in text.lines() {
+            let Some((name, key)) = line.split_once('=') else {
+                continue;
+            };
+            let Some(key) = parse_key(key.trim()) else {
+                continue;
+            };
+            if let Some(entry) = map.0.iter_mut().find(|(action, _)| action.name() == name.trim()) {
+                entry.1 = key;
+            }
         }
-        Paragraph::new(first.to_span())
-            .block(Card::BLOCK_FIRST)
-            .render(Rect::new(x, y, 5, 2), buf);
-        y += 2;
-        for i in 1..(self.0.len() - 1) {
-            Paragraph::new(self.0[i].to_span())
-                .block(Card::BLOCK_MIDDLE)
-                .render(Rect::new(x, y, 5, 2), buf);
-            y += 2;
+        map
+    }
+
+    fn load(path: &Path) -> Self {
+        fs::read_to_string(path).map(|text| Self::parse(&text)).unwrap_or_default()
+    }
+
+    fn action(&self, key: KeyCode) -> Option<Action> {
+        self.0.iter().find(|&&(_, k)| k == key).map(|&(action, _)| action)
+    }
+
+    // the key to show for an action in the status bar and help
+    fn label(&self, action: Action) -> String {
+        let key = self.0.iter().find(|&&(a, _)| a == action).map(|&(_, k)| k);
+        match key {
+            Some(KeyCode::Char(' ')) => "Space".to_string(),
+            Some(KeyCode::Char(c)) => c.to_string(),
+            Some(KeyCode::F(n)) => format!("F{n}"),
+            Some(KeyCode::Esc) => "Esc".to_string(),
+            Some(KeyCode::Enter) => "Enter".to_string(),
+            Some(KeyCode::Backspace) => "Backspace".to_string(),
+            _ => "?".to_string(),
         }
+    }
+}
 
-        Paragraph::new(self.0.last().unwrap().to_span())
-            .block(Card::BLOCK_LAST)
-            .render(Rect::new(x, y, 5, 5), buf);
+fn parse_key(s: &str) -> Option<KeyCode> {
+    let mut chars = s.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    match s.to_ascii_lowercase().as_str() {
+        "space" => Some(KeyCode::Char(' ')),
+        "esc" => Some(KeyCode::Esc),
+        "enter" => Some(KeyCode::Enter),
+        "backspace" => Some(KeyCode::Backspace),
+        f => f.strip_prefix('f')?.parse().ok().filter(|n| (1..=12).contains(n)).map(KeyCode::F),
     }
 }
 
-impl Widget for &Pile {
-    fn render(self, area: Rect, buf: &mut Buffer) {
-        let area = Rect::new(area.x, area.y, 5, 5);
-        if let Some(top) = self.0.last() {
-            Paragraph::new(top.to_span())
-                .block(Card::BLOCK_SINGLE)
-                .render(area, buf);
-            return
+// the options changed in the menu or with keys, kept for the next launch;
+// key bindings live in their own file
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Prefs {
+    variant: Variant,
+    draw_count: u8,
+    scoring: ScoringMode,
+    light: bool,
+    large_cards: bool,
+    click_mode: ClickMode,
+}
+
+impl Default for Prefs {
+    fn default() -> Self {
+        Self {
+            variant: Variant::Klondike,
+            draw_count: 1,
+            scoring: ScoringMode::Standard,
+            light: false,
+            large_cards: false,
+            click_mode: ClickMode::Select,
         }
-        Card::BLOCK_EMPTY.render(area, buf);
     }
 }
 
-impl Widget for &App {
-    fn render(self, area: Rect, buf: &mut Buffer) {
-        if area.width < 41 || area.height < 31 {
-            Span::raw("Too small")
-                .render(area, buf);
-            return;
+impl Prefs {
+    fn path() -> Option<PathBuf> {
+        home_file(".solitui_prefs")
+    }
+
+    // key=value per line like the stats; unknown keys and bad values keep the default
+    fn parse(text: &str) -> Self {
+        let mut prefs = Self::default();
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "game" => {
+                    prefs.variant = match value {
+                        "klondike" => Variant::Klondike,
+                        "freecell" => Variant::FreeCell,
+                        "spider" => Variant::Spider,
+                        _ => prefs.variant,
+                    }
+                }
+                "draw" => prefs.draw_count = value.parse().ok().filter(|n| [1, 3].contains(n)).unwrap_or(prefs.draw_count),
+                "scoring" => {
+                    prefs.scoring = match value {
+                        "standard" => ScoringMode::Standard,
+                        "vegas" => ScoringMode::Vegas,
+                        _ => prefs.scoring,
+                    }
+                }
+                "theme" => prefs.light = value == "light",
+                "large_cards" => prefs.large_cards = value.parse().unwrap_or(prefs.large_cards),
+                "click" => {
+                    prefs.click_mode = match value {
+                        "select" => ClickMode::Select,
+                        "place" => ClickMode::Place,
+                        _ => prefs.click_mode,
+                    }
+                }
+                _ => {}
+            }
         }
+        prefs
+    }
 
-        let mut x = area.x;
-        let mut y = area.y;
+    fn load(path: &Path) -> Self {
+        fs::read_to_string(path).map(|text| Self::parse(&text)).unwrap_or_default()
+    }
 
-        // columns
-        for row in &self.rows {
-            row.render(Rect::new(
-                x,
-                y,
-                5,
-                20
-            ), buf);
-            x += 5;
+    fn save(&self, path: &Path) -> io::Result<()> {
+        let variant = match self.variant {
+            Variant::Klondike => "klondike",
+            Variant::FreeCell => "freecell",
+            Variant::Spider => "spider",
+        };
+        let scoring = match self.scoring {
+            ScoringMode::Standard => "standard",
+            ScoringMode::Vegas => "vegas",
+        };
+        let theme = if self.light {"light"} else {"dark"};
+        let click = if self.click_mode == ClickMode::Place {"place"} else {"select"};
+        fs::write(path, format!(
+            "game={variant}\ndraw={}\nscoring={scoring}\ntheme={theme}\nlarge_cards={}\nclick={click}\n",
+            self.draw_count, self.large_cards
+        ))
+    }
+}
+
+// what the screen is showing, and so where input goes
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AppState {
+    // the options picked before the first deal
+    Menu,
+    // the saved game offered on launch, over the menu
+    ResumePrompt,
+    // a new deal going out, with this many tableau cards on the board so far
+    Dealing(usize),
+    Playing,
+    // every card is face-up and the stock is empty, so the rest could go up in one go
+    CollectPrompt,
+    // autocomplete asked for, waiting on a yes when --confirm-autocomplete is set
+    AutocompletePrompt,
+    Help,
+    // this deal's seed to share, and another being typed in to play
+    SeedPrompt,
+    ConfirmQuit,
+    // the won foundations cascading off the board
+    Celebrating,
+    // the last two end the run loop
+    Won,
+    Quit,
+}
+
+// what clicking a card does while nothing is selected
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClickMode {
+    // picks it up, to be put down by the next click
+    Select,
+    // sends it to a foundation if one takes it, and only picks it up otherwise
+    Place,
+}
+
+// which solitaire the menu deals
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Variant {
+    Klondike,
+    FreeCell,
+    Spider,
+}
+
+struct App {
+    state: AppState,
+    variant: Variant,
+    // the FreeCell deal when that is being played, everything Klondike is left idle meanwhile
+    freecell: Option<FreeCell>,
+    freecell_selected: Option<Spot>,
+    // when and where the last FreeCell click landed, for spotting double-clicks
+    freecell_last_click: Option<(Instant, Spot)>,
+    // where the left button went down on a FreeCell or Spider board, to tell a drag from a click
+    pressed: Option<(u16, u16)>,
+    spider: Option<Spider>,
+    // column and card picked up in Spider
+    spider_selected: Option<(usize, usize)>,
+    // highlighted line of the start menu
+    menu_row: usize,
+    // the seed of the daily deal, once one has been started
+    daily: Option<u64>,
+    // what has been typed into the seed prompt, and whether Enter turned it down
+    seed_input: String,
+    seed_invalid: bool,
+    game: Game,
+    selected_pos: SelectedPos,
+    // logical column shown in each on-screen slot
+    column_order: [usize; 7],
+    focus: Option<Focus>,
+    // when and where the last board click landed, for spotting double-clicks
+    last_click: Option<(Instant, SelectedPos)>,
+    drag: Option<Drag>,
+    start: Instant,
+    centiseconds: bool,
+    // set once this game has been counted in the stats
+    recorded: bool,
+    stats: Stats,
+    keymap: KeyMap,
+    // None keeps the stats in memory only, as the stress run does
+    stats_file: Option<PathBuf>,
+    // where s saves the game, None when there is no home directory
+    save_file: Option<PathBuf>,
+    // where changed options are kept, None to leave them for this run only
+    prefs_file: Option<PathBuf>,
+    // the prefs as the file has them, which only take the settings changed while playing
+    saved_prefs: Prefs,
+    click_mode: ClickMode,
+    show_stats: bool,
+    card: CardSize,
+    theme: Theme,
+    peek_stock: bool,
+    // --scoring none, for playing without a score at all
+    unscored: bool,
+    auto_stock: bool,
+    stalemate: bool,
+    foundation_hints: bool,
+    notice: Option<&'static str>,
+    // the board as it was dealt, what Restart goes back to; None for a save that didn't keep it
+    dealt: Option<Game>,
+    // where the last refused move was headed and why, until REFUSED_FOR has passed
+    refused: Option<(SelectedPos, String, Instant)>,
+    // redeals each new game starts with, None for no limit
+    redeal_limit: Option<u8>,
+    // points standard scoring takes for each undo, on top of handing back the move's own
+    undo_penalty: i32,
+    // undo entries each game keeps, see UNDO_LIMIT
+    undo_limit: usize,
+    // rules each new game is scored by
+    scoring: ScoringMode,
+    // Vegas winnings from the games already left behind this session
+    session_score: i32,
+    // source and destination of the suggested move, shown until the next input
+    hint: Option<(SelectedPos, SelectedPos)>,
+    // the card the last move picked up and where it went, for the side panel
+    last_move: Option<(Card, SelectedPos)>,
+    separators: bool,
+    auto_collect: bool,
+    collect_declined: bool,
+    // deals made since the last card went up, while autocomplete is running
+    autocomplete: Option<usize>,
+    confirm_autocomplete: bool,
+    verbose: bool,
+    log: Vec<String>,
+    debug_hitboxes: bool,
+    cascade: Option<Cascade>,
+    flight: Option<Flight>,
+    // frames a moved card takes to reach its new pile, 0 to move instantly
+    move_frames: u8,
+    // terminal size as of the last draw, so clicks can be mapped back onto the board
+    screen: Rect,
+    // set on resize so the next draw starts from a blank terminal
+    redraw: bool,
+}
+
+const LOG_LEN: usize = 12;
+
+// width of the info panel beside the board
+const PANEL_W: u16 = 32;
+
+// the mouse, after the keys in the help overlay
+const HELP_MOUSE: &str = "\
+click a card, then where it goes
+click it again to let go
+double-click or right-click sends it home
+or drag it onto a pile
+scroll over the stock to deal";
+
+const USAGE: &str = "\
+usage: solitui [options]
+
+  --seed N             play deal N
+  --daily              play today's deal
+  --freecell           play FreeCell
+  --spider             play one-suit Spider
+  --draw 1|3           cards turned per deal (--draw-three)
+  --scoring standard|vegas|none
+                       how the game is scored (--vegas)
+  --redeals N          passes through the stock allowed
+  --undo-penalty N     points each undo costs
+  --undo-limit N       moves that can be undone, 200 by default
+  --peek               show the next stock card, unscored in draw 1
+  --auto-stock         deal when nothing else can be played
+  --auto-collect       offer to finish a solved game
+  --confirm-autocomplete
+                       ask before autocomplete plays the game out
+  --move-speed off|fast|normal|slow
+  --click select|place what clicking a card does
+  --theme dark|light   (--light)
+  --ascii              letters for suits, square corners
+  --unicode-cards      playing card glyphs
+  --four-color         a color per suit
+  --card-size WxH      (--large-cards)
+  --card-back C        pattern on face-down cards
+  --background C       fill for the empty board
+  --felt               green board
+  --frame              border around the board
+  --align left|center|right
+  --separators         rules between the board areas
+  --foundation-hints   mark the cards the foundations need
+  --centiseconds       finer clock
+  --verbose            log refused moves
+  --print-deal         print the deal and exit
+  --stress N           play N games automatically and exit
+  --reset-stats        clear the stats and exit
+  --debug-hitboxes     tint the click regions
+  --help               show this";
+
+// an argument that can't be used stops the launch before the terminal is touched
+fn bad_arg(problem: &str) -> ! {
+    eprintln!("solitui: {problem}\n\n{USAGE}");
+    std::process::exit(2);
+}
+
+// the value after a flag, parsed, or the usage if it isn't usable
+fn arg_value<T>(args: &mut impl Iterator<Item = String>, flag: &str, parse: impl FnOnce(&str) -> Option<T>) -> T {
+    args.next().as_deref().and_then(parse).unwrap_or_else(|| bad_arg(&format!("bad or missing value for {flag}")))
+}
+
+// the start menu's lines, the last two deal today's game or a random one
+const MENU_ROWS: usize = 6;
+
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+const REFUSED_FOR: Duration = Duration::from_millis(1200);
+
+const UNDO_PENALTY: i32 = 2;
+
+const AUTOCOMPLETE_STEP: Duration = Duration::from_millis(80);
+
+const DEAL_STEP: Duration = Duration::from_millis(35);
+
+const MOVE_STEP: Duration = Duration::from_millis(16);
+const MOVE_FRAMES_NORMAL: u8 = 8;
+
+const CASCADE_STEP: Duration = Duration::from_millis(40);
+// cells per frame added to a falling card's speed, and how much of it survives a bounce
+const CASCADE_GRAVITY: f32 = 0.35;
+const CASCADE_BOUNCE: f32 = 0.75;
+
+// cards dealt out to the tableau, one more to each column on every pass
+const TABLEAU_CARDS: usize = 28;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Button {
+    NewGame,
+    Restart,
+    Undo,
+}
+
+impl Button {
+    const ALL: [Self; 3] = [Button::NewGame, Button::Restart, Button::Undo];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Button::NewGame => " New game ",
+            Button::Restart => " Restart ",
+            Button::Undo => " Undo ",
         }
+    }
+}
 
-        x += 1;
-        // stock
-        self.stock.render(Rect::new(
-            x,
-            y,
-            5,
-            5
-        ), buf);
-        y += 5;
+// cards picked up with the mouse, following the cursor until they are dropped
+#[derive(Debug, Clone, Copy)]
+struct Drag {
+    src: SelectedPos,
+    // board-relative cells where the button went down and where the cursor is now
+    from: (u16, u16),
+    at: (u16, u16),
+}
 
-        // discard
-        self.discard.render(Rect::new(
-            x,
-            y,
-            5,
-            4
-        ), buf);
-        y += 5;
+// cards just moved, drawn on their way over from where they were while their new pile
+// holds off showing them
+struct Flight {
+    dest: SelectedPos,
+    count: usize,
+    // board-relative top card rects at either end
+    from: Rect,
+    to: Rect,
+    frame: u8,
+}
 
-        // suit piles
-        for i in 0..4 {
-            self.suit_piles[i].render(Rect::new(
-                x,
-                y,
-                5,
-                5
-            ), buf);
-            y += 5;
+// the won foundations leaving the board a card at a time, kings first, each bouncing along
+// the bottom and leaving a copy of itself at every spot it passes
+struct Cascade {
+    // board cells of the four foundations
+    from: [(u16, u16); 4],
+    // cards sent off so far, including the one in flight
+    launched: usize,
+    // board cells, and cells per frame
+    pos: (f32, f32),
+    speed: (f32, f32),
+    trail: Vec<(u16, u16, Card)>,
+}
+
+impl Cascade {
+    fn new(from: [(u16, u16); 4]) -> Self {
+        let mut res = Self { from, launched: 0, pos: (0.0, 0.0), speed: (0.0, 0.0), trail: Vec::new() };
+        res.launch();
+        res
+    }
+
+    // sends the next card off its foundation, leftward at a random speed
+    fn launch(&mut self) {
+        let (x, y) = self.from[self.launched % 4];
+        self.pos = (x as f32, y as f32);
+        self.speed = (-1.0 - rand::random::<f32>() * 1.5, -rand::random::<f32>() * 1.5);
+        self.launched += 1;
+    }
+
+    // cards taken off foundation n so far
+    fn taken(&self, n: usize) -> usize {
+        (self.launched + 3 - n) / 4
+    }
+
+    // one frame on, false once the last card has left the board
+    fn step(&mut self, size: CardSize, piles: &[Pile; 4]) -> bool {
+        let floor = size.board_height().saturating_sub(size.height) as f32;
+        self.speed.1 += CASCADE_GRAVITY;
+        self.pos.0 += self.speed.0;
+        self.pos.1 = (self.pos.1 + self.speed.1).max(0.0);
+        if self.pos.1 > floor {
+            self.pos.1 = floor;
+            self.speed.1 *= -CASCADE_BOUNCE;
+        }
+        if self.pos.0 < 0.0 || self.pos.0 + size.width as f32 > size.board_width() as f32 {
+            if self.launched == 52 {
+                return false;
+            }
+            self.launch();
+            return true;
+        }
+        let n = self.launched - 1;
+        let card = piles[n % 4].0[12 - n / 4];
+        let at = (self.pos.0 as u16, self.pos.1 as u16);
+        if self.trail.last().map(|&(x, y, _)| (x, y)) != Some(at) {
+            self.trail.push((at.0, at.1, card));
         }
+        true
     }
 }
 
-fn main() -> io::Result<()> {
-    let mut app = App::init();
-    let mut terminal = ratatui::init();
-    execute!(io::stdout(), EnableMouseCapture).unwrap();
-    let res = app.run(&mut terminal);
-    ratatui::restore();
-    res
+// keyboard cursor, Tab jumps between regions and arrows (or hjkl) move pile to pile
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Focus {
+    // on-screen slot, not the logical column, and how many cards up from the top
+    Tableau(usize, usize),
+    Stock,
+    Discard,
+    Foundation(usize),
 }
 
+impl Focus {
+    // Tab order, each region entered at its first pile
+    const REGIONS: [Self; 4] = [Focus::Tableau(0, 0), Focus::Stock, Focus::Discard, Focus::Foundation(0)];
+
+    // the right-hand column, top to bottom
+    const PILES: [Self; 6] = [
+        Focus::Stock, Focus::Discard,
+        Focus::Foundation(0), Focus::Foundation(1), Focus::Foundation(2), Focus::Foundation(3)
+    ];
+
+    fn region(&self) -> usize {
+        match self {
+            Focus::Tableau(..) => 0,
+            Focus::Stock => 1,
+            Focus::Discard => 2,
+            Focus::Foundation(_) => 3,
+        }
+    }
+}
+
+impl App {
+    fn init(seed: Option<u64>) -> Self {
+        let mut res = Self {
+            state: AppState::Playing,
+            variant: Variant::Klondike,
+            freecell: None,
+            freecell_selected: None,
+            freecell_last_click: None,
+            pressed: None,
+            spider: None,
+            spider_selected: None,
+            menu_row: 0,
+            daily: None,
+            seed_input: String::new(),
+            seed_invalid: false,
+            // replaced by the real deal below
+            game: Game::new(0, 1),
+            selected_pos: SelectedPos::None,
+            column_order: [0, 1, 2, 3, 4, 5, 6],
+            focus: None,
+            last_click: None,
+            drag: None,
+            start: Instant::now(),
+            centiseconds: false,
+            recorded: false,
+            stats: Stats::default(),
+            keymap: KeyMap::default(),
+            stats_file: None,
+            save_file: None,
+            prefs_file: None,
+            saved_prefs: Prefs::default(),
+            click_mode: ClickMode::Select,
+            show_stats: false,
+            card: CardSize::default(),
+            theme: Theme::default(),
+            peek_stock: false,
+            unscored: false,
+            auto_stock: false,
+            stalemate: false,
+            foundation_hints: false,
+            notice: None,
+            refused: None,
+            redeal_limit: None,
+            undo_penalty: UNDO_PENALTY,
+            undo_limit: UNDO_LIMIT,
+            scoring: ScoringMode::Standard,
+            session_score: 0,
+            hint: None,
+            last_move: None,
+            separators: false,
+            auto_collect: false,
+            collect_declined: false,
+            autocomplete: None,
+            confirm_autocomplete: false,
+            verbose: false,
+            log: Vec::new(),
+            debug_hitboxes: false,
+            cascade: None,
+            flight: None,
+            move_frames: MOVE_FRAMES_NORMAL,
+            screen: Rect::default(),
+            redraw: false,
+            dealt: None
+        };
+
+        res.new_game(seed);
+        res
+    }
+
+    // deals a fresh board, keeping the display and play options
+    fn new_game(&mut self, seed: Option<u64>) {
+        // random deals get a seed too, so any game can be named and replayed
+        self.play(Game::new(seed.unwrap_or_else(rand::random), self.game.draw_count));
+    }
+
+    // starts over on a board straight from the deal
+    fn play(&mut self, game: Game) {
+        self.end_game();
+        // the buy-in is only lost on deals that were actually played
+        if self.game.scoring == ScoringMode::Vegas && self.game.moves > 0 {
+            self.session_score += self.game.score;
+        }
+
+        self.game = game;
+        self.dealt = Some(self.game.clone());
+        self.game.redeals_remaining = self.redeal_limit;
+        self.game.undo_limit = self.undo_limit;
+        self.game.set_scoring(self.scoring);
+        self.selected_pos = SelectedPos::None;
+        self.start = Instant::now();
+        self.recorded = false;
+        self.stalemate = false;
+        if matches!(self.state, AppState::CollectPrompt | AppState::AutocompletePrompt) {
+            self.state = AppState::Playing;
+        }
+        self.collect_declined = false;
+        self.autocomplete = None;
+        self.cascade = None;
+        self.flight = None;
+        self.last_move = None;
+
+        // still playable through the stock, but worth flagging so it doesn't look broken
+        self.notice = self.game.legal_moves().is_empty().then_some("No opening moves, deal to start");
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<(), Error> {
+        if self.state == AppState::Playing {
+            self.auto_advance();
+        }
+        while !matches!(self.state, AppState::Won | AppState::Quit) {
+            if self.refused.as_ref().is_some_and(|(_, _, at)| at.elapsed() >= REFUSED_FOR) {
+                self.refused = None;
+            }
+            if std::mem::take(&mut self.redraw) {
+                terminal.clear()?;
+            }
+            let size = terminal.size()?;
+            self.screen = Rect::new(0, 0, size.width, size.height);
+            terminal.draw(|frame| self.draw(frame))?;
+            // wake up when the clock would show a new value, not before
+            let wait = match self.state {
+                AppState::Dealing(_) => DEAL_STEP,
+                AppState::Celebrating => CASCADE_STEP,
+                _ if self.flight.is_some() => MOVE_STEP,
+                _ if self.autocomplete.is_some() => AUTOCOMPLETE_STEP,
+                _ => self.clock_wait(),
+            };
+            // and again when a refused move stops showing
+            let wait = self.refused.as_ref().map_or(wait, |(_, _, at)| wait.min(REFUSED_FOR.saturating_sub(at.elapsed())));
+            if event::poll(wait)? {
+                self.handle_events()?
+            } else if let AppState::Dealing(shown) = self.state {
+                self.deal_step(shown);
+            } else if self.state == AppState::Celebrating {
+                self.cascade_step();
+            } else if let Some(flight) = &mut self.flight {
+                flight.frame += 1;
+                if flight.frame >= self.move_frames {
+                    self.flight = None;
+                }
+            } else if self.autocomplete.is_some() {
+                self.autocomplete_step();
+            }
+        }
+        self.end_session()
+    }
+
+    // an unfinished game is saved to carry on with next time rather than counted as a loss
+    fn end_session(&mut self) -> Result<(), Error> {
+        if let Some(path) = self.save_file.clone() {
+            if self.game.check_win() {
+                if path.exists() {
+                    fs::remove_file(&path)?;
+                }
+            } else if self.game.moves > 0 {
+                return self.save(&path);
+            }
+        }
+        self.end_game();
+        Ok(())
+    }
+
+    // counts the game in the stats once, as a loss unless it was won; untouched deals don't count
+    fn end_game(&mut self) {
+        let (won, moves, score) = if let Some(fc) = &self.freecell {
+            (fc.check_win(), fc.moves, None)
+        } else if let Some(sp) = &self.spider {
+            (sp.check_win(), sp.moves, None)
+        } else {
+            (self.game.check_win(), self.game.moves, self.standard_score())
+        };
+        if self.recorded || moves == 0 {
+            return;
+        }
+        self.recorded = true;
+        self.stats.record(won, self.start.elapsed().as_secs(), moves, score);
+        if let Some(path) = &self.stats_file {
+            if let Err(err) = self.stats.save(path) {
+                self.log(format!("saving stats: {err}"));
+            }
+        }
+    }
+
+    // seeing the next card is a teaching aid, so those games go unscored
+    fn scored(&self) -> bool {
+        !(self.unscored || self.peek_stock && self.game.draw_count == 1)
+    }
+
+    // the score worth comparing against the best; Vegas money is a different measure
+    fn standard_score(&self) -> Option<i32> {
+        (self.scored() && self.game.scoring == ScoringMode::Standard).then_some(self.game.score)
+    }
+
+    fn clock_tick(&self) -> Duration {
+        if self.centiseconds {Duration::from_millis(10)} else {Duration::from_secs(1)}
+    }
+
+    fn clock_wait(&self) -> Duration {
+        let tick = self.clock_tick().as_nanos();
+        let elapsed = self.start.elapsed().as_nanos();
+        Duration::from_nanos((tick - elapsed % tick) as u64)
+    }
+
+    fn clock(&self) -> String {
+        let elapsed = self.start.elapsed();
+        let secs = elapsed.as_secs();
+        if self.centiseconds {
+            format!("{:02}:{:02}.{:02}", secs / 60, secs % 60, elapsed.subsec_millis() / 10)
+        } else {
+            format!("{:02}:{:02}", secs / 60, secs % 60)
+        }
+    }
+    
+    fn draw(&self, frame: &mut Frame) {
+        frame.render_widget(self, frame.area());
+    }
+
+    fn handle_events(&mut self) -> Result<(), Error> {
+        let before = self.game.state_hash();
+        self.hint = None;
+        // a card still on its way lands at once rather than hold up the input
+        self.flight = None;
+        self.handle_event(event::read()?);
+        let idle = matches!(self.state, AppState::Menu | AppState::ResumePrompt | AppState::Dealing(_) | AppState::Celebrating | AppState::Won | AppState::Quit);
+        if idle || self.freecell.is_some() || self.spider.is_some() {
+            return Ok(());
+        }
+        if self.game.state_hash() != before {
+            self.stalemate = false;
+            self.notice = None;
+        }
+        self.auto_advance();
+        self.offer_collect();
+        Ok(())
+    }
+
+    fn handle_event(&mut self, ev: Event) {
+        match ev {
+            Event::Resize(width, height) => {
+                // the board may have moved, or be replaced by the too-small message
+                self.screen = Rect::new(0, 0, width, height);
+                self.last_click = None;
+                self.redraw = true;
+            }
+            Event::Key(_) if self.state == AppState::Help => {self.state = AppState::Playing}
+            // the help covers the board, so clicks don't reach the cards under it
+            Event::Mouse(_) if self.state == AppState::Help => {}
+            Event::Key(ev) if self.state == AppState::ConfirmQuit => {
+                match ev.code {
+                    KeyCode::Char('y') | KeyCode::Enter => {self.state = AppState::Quit}
+                    KeyCode::Char('n') | KeyCode::Esc => {self.state = AppState::Playing}
+                    _ => {}
+                }
+            }
+            // a stray click shouldn't answer the quit dialog
+            Event::Mouse(_) if self.state == AppState::ConfirmQuit => {}
+            Event::Key(ev) if self.state == AppState::SeedPrompt => {self.seed_key(ev.code)}
+            Event::Mouse(_) if self.state == AppState::SeedPrompt => {}
+            Event::Key(ev) if self.state == AppState::ResumePrompt => {
+                match ev.code {
+                    KeyCode::Char('y') | KeyCode::Enter => {self.resume()}
+                    KeyCode::Char('n') | KeyCode::Esc => {self.state = AppState::Menu}
+                    _ => {}
+                }
+            }
+            Event::Key(ev) if self.state == AppState::Menu => {self.menu_key(ev.code)}
+            // any key cuts the celebration short
+            Event::Key(_) if self.state == AppState::Celebrating => {self.state = AppState::Won}
+            Event::Mouse(_) if self.state == AppState::Celebrating => {}
+            Event::Key(ev) if self.freecell.is_some() => {self.freecell_key(ev.code)}
+            Event::Mouse(ev) if self.freecell.is_some() => {self.freecell_mouse(ev)}
+            Event::Key(ev) if self.spider.is_some() => {self.spider_key(ev.code)}
+            Event::Mouse(ev) if self.spider.is_some() => {self.spider_mouse(ev)}
+            Event::Mouse(_) if matches!(self.state, AppState::Menu | AppState::ResumePrompt) => {}
+            // any key skips to the dealt board
+            Event::Key(_) if matches!(self.state, AppState::Dealing(_)) => {self.finish_dealing()}
+            Event::Mouse(_) if matches!(self.state, AppState::Dealing(_)) => {}
+            Event::Key(ev) if self.state == AppState::CollectPrompt => {
+                match ev.code {
+                    KeyCode::Char('y') | KeyCode::Enter => {
+                        self.state = AppState::Playing;
+                        self.collect();
+                    }
+                    KeyCode::Char('n') | KeyCode::Esc => {
+                        self.state = AppState::Playing;
+                        self.collect_declined = true;
+                    }
+                    _ => {}
+                }
+            }
+            // the board is behind the prompt, so clicks wait for an answer
+            Event::Mouse(_) if self.state == AppState::CollectPrompt => {}
+            Event::Key(ev) if self.state == AppState::AutocompletePrompt => {
+                match ev.code {
+                    KeyCode::Char('y') | KeyCode::Enter => {
+                        self.state = AppState::Playing;
+                        self.run_autocomplete();
+                    }
+                    KeyCode::Char('n') | KeyCode::Esc => {self.state = AppState::Playing}
+                    _ => {}
+                }
+            }
+            Event::Mouse(_) if self.state == AppState::AutocompletePrompt => {}
+            Event::Key(ev) => {
+                // any key takes back control from a running autocomplete
+                self.stop_autocomplete();
+                if let Some(action) = self.keymap.action(ev.code) {
+                    self.perform(action);
+                    return;
+                }
+                match ev.code {
+                    KeyCode::F(12) => {self.debug_hitboxes = !self.debug_hitboxes}
+                    KeyCode::Char('v') => {self.verbose = !self.verbose}
+                    KeyCode::Char('g') => {self.separators = !self.separators}
+                    KeyCode::Char('t') => {self.show_stats = !self.show_stats}
+                    KeyCode::Char('?') => {self.state = AppState::Help}
+                    KeyCode::Char('T') => {
+                        self.theme.toggle_light();
+                        self.save_pref(|saved, now| saved.light = now.light);
+                    }
+                    KeyCode::Char('L') => {
+                        self.toggle_large_cards();
+                        self.save_pref(|saved, now| saved.large_cards = now.large_cards);
+                    }
+                    KeyCode::Char('e') => {self.foundation_hints = !self.foundation_hints}
+                    KeyCode::Char('w') => {
+                        self.game.draw_count = if self.game.draw_count == 1 {3} else {1};
+                        self.save_pref(|saved, now| saved.draw_count = now.draw_count);
+                    }
+                    KeyCode::Char('r') => {self.press(Button::Restart)}
+                    KeyCode::Char('b') => {
+                        self.game.save_branch();
+                        self.notice = Some("Line saved, B comes back to it");
+                    }
+                    KeyCode::Char('B') => {
+                        self.selected_pos = SelectedPos::None;
+                        if !self.game.return_to_branch() {
+                            self.notice = Some("No saved line, b saves one");
+                        }
+                    }
+                    KeyCode::Char('[') => {self.shift_column(-1)}
+                    KeyCode::Char(']') => {self.shift_column(1)}
+                    KeyCode::Tab => {self.cycle_focus(true)}
+                    KeyCode::BackTab => {self.cycle_focus(false)}
+                    KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down => {self.move_focus(ev.code)}
+                    KeyCode::Char('h') => {self.move_focus(KeyCode::Left)}
+                    KeyCode::Char('j') => {self.move_focus(KeyCode::Down)}
+                    KeyCode::Char('k') => {self.move_focus(KeyCode::Up)}
+                    KeyCode::Char('l') => {self.move_focus(KeyCode::Right)}
+                    KeyCode::Enter => {
+                        if let Some(focus) = self.focus {
+                            let new_pos = self.focus_pos(focus);
+                            self.click(new_pos);
+                        }
+                    }
+                    KeyCode::Char('s') => {self.save_game()}
+                    KeyCode::Char('S') => {
+                        self.seed_input.clear();
+                        self.seed_invalid = false;
+                        self.state = AppState::SeedPrompt;
+                    }
+                    KeyCode::Char('f') => {self.deal_and_play()}
+                    KeyCode::Char(c @ '1'..='7') => {self.pick_slot(c as usize - '1' as usize)}
+                    KeyCode::Char('8') => {self.click(self.focus_pos(Focus::Discard))}
+                    KeyCode::Char('9') => {self.send_home(self.selected_pos);}
+                    _ => {}
+                }
+            }
+            Event::Mouse(ev) => {
+                let margin = self.theme.margin();
+                let board = self.layout(self.screen);
+                let (Some(x), Some(y)) = (ev.column.checked_sub(board.x + margin), ev.row.checked_sub(board.y + margin)) else {
+                    // let go off the board, so whatever was dragged stays put
+                    if matches!(ev.kind, MouseEventKind::Up(_)) {
+                        self.drag = None;
+                    }
+                    return;
+                };
+
+                match ev.kind {
+                    MouseEventKind::Down(event::MouseButton::Left) => {
+                        let src = self.hit_test(x as usize, y as usize);
+                        self.drag = (!self.drag_run(src).is_empty()).then_some(Drag { src, from: (x, y), at: (x, y) });
+                    }
+                    MouseEventKind::Drag(event::MouseButton::Left) => {
+                        if let Some(drag) = &mut self.drag {
+                            drag.at = (x, y);
+                        }
+                    }
+                    MouseEventKind::Up(event::MouseButton::Left) => self.release(x, y),
+                    MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
+                        // the wheel over the stock or discard deals, recycling like a click would
+                        let at = Position::new(x, y);
+                        let over = [Focus::Stock, Focus::Discard].into_iter().any(|f| self.pile_rect(f).contains(at))
+                            || self.hit_test(x as usize, y as usize) == SelectedPos::Discard;
+                        if over {
+                            self.game.deal();
+                        }
+                    }
+                    // the one-click way home, anything that can't go up is left alone
+                    MouseEventKind::Up(event::MouseButton::Right) => {self.send_home(self.hit_test(x as usize, y as usize));}
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // a drag that ends on another pile is a move, anything else counts as a click
+    fn release(&mut self, x: u16, y: u16) {
+        let new_pos = self.hit_test(x as usize, y as usize);
+        if let Some(drag) = self.drag.take() {
+            let same_pile = match (drag.src, new_pos) {
+                (SelectedPos::Column(a, _), SelectedPos::Column(b, _)) => a == b,
+                (a, b) => a == b,
+            };
+            if drag.from != (x, y) && !same_pile {
+                self.last_click = None;
+                self.try_move(drag.src, new_pos);
+                return;
+            }
+        }
+
+        if let Some(button) = self.button_at(x, y) {
+            self.press(button);
+            return;
+        }
+
+        let now = Instant::now();
+        // the stock deals on every click, so it never counts as a double-click
+        let double = new_pos != SelectedPos::Stock && self.last_click
+            .is_some_and(|(at, pos)| pos == new_pos && now - at < DOUBLE_CLICK);
+        if double {
+            self.last_click = None;
+            // a card with nowhere to go is still the selection, and a second click drops it
+            if !self.send_home(new_pos) && new_pos == self.selected_pos {
+                self.click(new_pos);
+            }
+            return;
+        }
+        self.last_click = Some((now, new_pos));
+        if self.click_mode == ClickMode::Place && self.selected_pos == SelectedPos::None && self.send_home(new_pos) {
+            return;
+        }
+        self.click(new_pos);
+    }
+
+    // the cards a drag starting at pos picks up, empty when there is nothing to take
+    fn drag_run(&self, pos: SelectedPos) -> &[Card] {
+        let top = |pile: &Pile| -> usize {pile.0.len().saturating_sub(1)};
+        match pos {
+            SelectedPos::Column(x, y) => {
+                let col = &self.game.rows[x].0;
+                if y < col.len() && is_valid_run(&col[y..]) {&col[y..]} else {&[]}
+            }
+            SelectedPos::Discard => &self.game.discard.0[top(&self.game.discard)..],
+            SelectedPos::SuitPile(n) => &self.game.suit_piles[n].0[top(&self.game.suit_piles[n])..],
+            SelectedPos::None | SelectedPos::Stock => &[],
+        }
+    }
+
+    fn perform(&mut self, action: Action) {
+        match action {
+            Action::Draw => {self.game.deal();}
+            Action::ClearSelection => self.selected_pos = SelectedPos::None,
+            Action::NewGame => self.press(Button::NewGame),
+            Action::Undo => self.undo(),
+            Action::Hint => self.show_hint(),
+            Action::Quit => self.quit(),
+            Action::Autocomplete => self.start_autocomplete(),
+        }
+    }
+
+    // counts the win, then sends the cards cascading off before the run ends
+    fn won(&mut self) {
+        self.end_game();
+        self.flight = None;
+        let (w, h) = (self.card.width, self.card.height);
+        let from = if self.freecell.is_some() {
+            [0, 1, 2, 3].map(|n| ((4 + n) * w, 0))
+        } else if self.spider.is_some() {
+            // the cleared runs are already off the board, so there is nothing to send off
+            self.state = AppState::Won;
+            return;
+        } else {
+            [0, 1, 2, 3].map(|n| (self.card.piles_x(), (2 + n) * h))
+        };
+        self.cascade = Some(Cascade::new(from));
+        self.state = AppState::Celebrating;
+    }
+
+    fn cascade_step(&mut self) {
+        let Some(cascade) = &mut self.cascade else {
+            return;
+        };
+        let piles = self.freecell.as_ref().map_or(&self.game.suit_piles, |fc| &fc.foundations);
+        if !cascade.step(self.card, piles) {
+            self.state = AppState::Won;
+        }
+    }
+
+    // only asks when there is a game in progress to walk away from
+    fn quit(&mut self) {
+        let other_moves = self.freecell.as_ref().map(|fc| fc.moves).or(self.spider.as_ref().map(|sp| sp.moves));
+        if let Some(moves) = other_moves {
+            self.state = if moves == 0 {AppState::Quit} else {AppState::ConfirmQuit};
+            return;
+        }
+        if self.game.moves == 0 || self.game.check_win() {
+            self.state = AppState::Quit;
+        } else {
+            self.state = AppState::ConfirmQuit;
+        }
+    }
+
+    // the key list plus the rules this game is being played under
+    fn help(&self) -> String {
+        let key = |action| self.keymap.label(action);
+        let keys = [
+            (key(Action::Draw), "deal"),
+            ("f".to_string(), "deal and play"),
+            (key(Action::Undo), "undo"),
+            (key(Action::Autocomplete), "autocomplete"),
+            (key(Action::Hint), "hint"),
+            (key(Action::ClearSelection), "clear selection"),
+            (key(Action::NewGame), "new game"),
+            ("r".to_string(), "restart deal"),
+            ("b".to_string(), "save this line"),
+            ("B".to_string(), "back to saved line"),
+            ("s".to_string(), "save"),
+            ("S".to_string(), "seed"),
+            ("w".to_string(), "draw 1 / draw 3"),
+            ("t".to_string(), "stats"),
+            ("T".to_string(), "light / dark"),
+            ("L".to_string(), "large cards"),
+            ("e".to_string(), "needed cards"),
+            ("g".to_string(), "separators"),
+            ("[ ]".to_string(), "shift column"),
+            ("1-7".to_string(), "pick column"),
+            ("8".to_string(), "pick discard"),
+            ("9".to_string(), "send home"),
+            ("v".to_string(), "move log"),
+            ("Tab".to_string(), "next region"),
+            (key(Action::Quit), "quit"),
+        ];
+        let table: Vec<String> = keys.chunks(2)
+            .map(|pair| match pair {
+                [(k1, a1), (k2, a2)] => format!("{k1:<6}{a1:<16}{k2:<4}{a2}"),
+                [(k, a)] => format!("{k:<6}{a}"),
+                _ => unreachable!(),
+            })
+            .collect();
+        let scoring = match (self.scored(), self.game.scoring) {
+            (false, _) if self.unscored => "unscored",
+            (false, _) => "unscored, peeking",
+            (true, ScoringMode::Standard) => "scored",
+            (true, ScoringMode::Vegas) => "Vegas scoring",
+        };
+        let redeals = self.game.redeals_remaining.map_or("no redeal limit".to_string(), |n| format!("{n} redeals left"));
+        // both ways of clicking, the one in use marked; --click or click= in ~/.solitui_prefs picks
+        let (select, place) = if self.click_mode == ClickMode::Place {(" ", ">")} else {(">", " ")};
+        format!(
+            "{}\narrows or hjkl move the cursor\nEnter picks up and puts down\n\n{HELP_MOUSE}\n\n\
+             {select} click=select  a click picks a card up\n\
+             {place} click=place   a click sends it home if it can\n\n\
+             draw {}, {scoring}, {redeals}\nany key to close",
+            table.join("\n"), self.game.draw_count
+        )
+    }
+
+    // typed characters build up a seed, Enter deals it and Esc goes back to the current game
+    fn seed_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Esc => self.state = AppState::Playing,
+            KeyCode::Backspace => {
+                self.seed_input.pop();
+                self.seed_invalid = false;
+            }
+            KeyCode::Enter if self.seed_input.is_empty() => self.state = AppState::Playing,
+            KeyCode::Enter => match self.seed_input.parse() {
+                Ok(seed) => self.start(Some(seed)),
+                Err(_) => self.seed_invalid = true,
+            },
+            // a u64 never needs more digits than this
+            KeyCode::Char(c) if self.seed_input.len() < 20 => {
+                self.seed_input.push(c);
+                self.seed_invalid = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn seed_prompt(&self) -> String {
+        let invalid = if self.seed_invalid {"a seed is a whole number, digits only"} else {""};
+        format!(
+            "This deal's seed\n\n  {}\n\nType a seed and press Enter to play it\n> {}_\n{invalid}\nEsc to go back",
+            self.game.seed, self.seed_input
+        )
+    }
+
+    // the keys a new player needs, under the board
+    fn status_bar(&self) -> String {
+        let key = |action| self.keymap.label(action);
+        format!(
+            "{} deal  {} undo  {} hint  {} new game  ? help  {} quit",
+            key(Action::Draw), key(Action::Undo), key(Action::Hint), key(Action::NewGame), key(Action::Quit)
+        )
+    }
+
+    fn save_path() -> Option<PathBuf> {
+        home_file(".solitui_save")
+    }
+
+    // the game, the selection and the time played so far; prompts and toggles aren't saved
+    fn save(&self, path: &Path) -> Result<(), Error> {
+        let app = format!(
+            "selected={}\nelapsed_ms={}\n",
+            self.selected_pos.token(), self.start.elapsed().as_millis()
+        );
+        let mut text = self.game.to_save() + &app;
+        // the deal goes along for Restart, its keys prefixed to keep them apart from the board's
+        if let Some(dealt) = &self.dealt {
+            for line in dealt.to_save().lines() {
+                text += &format!("dealt_{line}\n");
+            }
+        }
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    // a saved game still worth offering, one that was won or can't be read isn't
+    fn saved_game(path: &Path) -> Option<Game> {
+        let game = Game::parse(&fs::read_to_string(path).ok()?)?;
+        (!game.check_win()).then_some(game)
+    }
+
+    fn load(&mut self, path: &Path) -> Result<(), Error> {
+        let text = fs::read_to_string(path)?;
+        let game = Game::parse(&text).ok_or(Error::BadSave)?;
+        self.new_game(Some(game.seed));
+        self.game = game;
+        self.game.undo_limit = self.undo_limit;
+        self.notice = None;
+        let dealt: String = text.lines().filter_map(|line| line.strip_prefix("dealt_")).map(|line| format!("{line}\n")).collect();
+        self.dealt = Game::parse(&dealt);
+        let value = |key: &str| text.lines().find_map(|line| line.strip_prefix(key)?.strip_prefix('='));
+        // an edited save may point past the end of a column or at a face-down card, which is no selection at all
+        self.selected_pos = value("selected")
+            .and_then(SelectedPos::parse_token)
+            .map_or(SelectedPos::None, |pos| self.game.selectable(pos));
+        // the clock picks up where it stopped
+        let elapsed = value("elapsed_ms").and_then(|ms| ms.parse().ok()).map_or(Duration::ZERO, Duration::from_millis);
+        self.start = Instant::now().checked_sub(elapsed).unwrap_or(self.start);
+        Ok(())
+    }
+
+    fn save_game(&mut self) {
+        let Some(path) = self.save_file.clone() else {
+            return;
+        };
+        match self.save(&path) {
+            Ok(()) => self.notice = Some("Game saved"),
+            Err(err) => self.log(format!("saving game: {err}")),
+        }
+    }
+
+    fn resume(&mut self) {
+        let Some(path) = self.save_file.clone() else {
+            return;
+        };
+        match self.load(&path) {
+            Ok(()) => self.state = AppState::Playing,
+            Err(err) => {
+                self.log(format!("loading game: {err}"));
+                self.state = AppState::Menu;
+            }
+        }
+    }
+
+    fn menu_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => {self.menu_row = self.menu_row.saturating_sub(1)}
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => {self.menu_row = (self.menu_row + 1).min(MENU_ROWS - 1)}
+            KeyCode::Left | KeyCode::Right | KeyCode::Char('h') | KeyCode::Char('l') | KeyCode::Char(' ') => {
+                self.change_option(self.menu_row)
+            }
+            KeyCode::Enter if self.menu_row < MENU_ROWS - 2 => {self.change_option(self.menu_row)}
+            KeyCode::Enter if self.menu_row == MENU_ROWS - 2 => {self.start_daily()}
+            KeyCode::Enter => {self.start(None)}
+            KeyCode::Esc => {self.state = AppState::Quit}
+            code if self.keymap.action(code) == Some(Action::Quit) => {self.state = AppState::Quit}
+            _ => {}
+        }
+    }
+
+    // flips the option on a menu line, the game itself is only dealt once the menu is left
+    fn change_option(&mut self, row: usize) {
+        let keep: fn(&mut Prefs, &Prefs) = match row {
+            0 => {
+                self.variant = match self.variant {
+                    Variant::Klondike => Variant::FreeCell,
+                    Variant::FreeCell => Variant::Spider,
+                    Variant::Spider => Variant::Klondike,
+                };
+                |saved, now| saved.variant = now.variant
+            }
+            1 => {
+                self.game.draw_count = if self.game.draw_count == 1 {3} else {1};
+                |saved, now| saved.draw_count = now.draw_count
+            }
+            2 => {
+                self.scoring = match self.scoring {
+                    ScoringMode::Standard => ScoringMode::Vegas,
+                    ScoringMode::Vegas => ScoringMode::Standard,
+                };
+                |saved, now| saved.scoring = now.scoring
+            }
+            3 => {
+                self.theme.toggle_light();
+                |saved, now| saved.light = now.light
+            }
+            _ => return,
+        };
+        self.save_pref(keep);
+    }
+
+    fn prefs(&self) -> Prefs {
+        Prefs {
+            variant: self.variant,
+            draw_count: self.game.draw_count,
+            scoring: self.scoring,
+            light: self.theme.light,
+            large_cards: self.card == CardSize::LARGE,
+            click_mode: self.click_mode,
+        }
+    }
+
+    fn apply_prefs(&mut self, prefs: Prefs) {
+        self.variant = prefs.variant;
+        self.game.draw_count = prefs.draw_count;
+        self.scoring = prefs.scoring;
+        self.game.set_scoring(prefs.scoring);
+        if prefs.light != self.theme.light {
+            self.theme.toggle_light();
+        }
+        if prefs.large_cards {
+            self.card = CardSize::LARGE;
+        }
+        self.click_mode = prefs.click_mode;
+    }
+
+    // keep copies the setting just changed from the current prefs to the saved ones; the rest
+    // are written back as loaded, so one-offs like --draw 3 or --vegas aren't remembered
+    fn save_pref(&mut self, keep: fn(&mut Prefs, &Prefs)) {
+        let now = self.prefs();
+        keep(&mut self.saved_prefs, &now);
+        let Some(path) = self.prefs_file.clone() else {
+            return;
+        };
+        if let Err(err) = self.saved_prefs.save(&path) {
+            self.log(format!("saving preferences: {err}"));
+        }
+    }
+
+    // deals the variant picked in the menu or on the command line
+    fn start(&mut self, seed: Option<u64>) {
+        match self.variant {
+            Variant::Klondike => {
+                self.new_game(seed);
+                self.state = AppState::Dealing(0);
+            }
+            Variant::FreeCell => {
+                self.new_freecell(seed);
+                self.state = AppState::Playing;
+            }
+            Variant::Spider => {
+                self.new_spider(seed);
+                self.state = AppState::Playing;
+            }
+        }
+    }
+
+    fn start_daily(&mut self) {
+        let seed = daily_seed();
+        self.daily = Some(seed);
+        self.start(Some(seed));
+    }
+
+    // the seed line of the info panel, which also dates the daily deal
+    fn seed_label(&self, seed: u64) -> String {
+        if self.daily != Some(seed) {
+            return format!("Seed {seed}");
+        }
+        format!("Seed {seed}  daily {}-{:02}-{:02}", seed / 10_000, seed / 100 % 100, seed % 100)
+    }
+
+    fn menu(&self) -> String {
+        let scoring = match self.scoring {
+            ScoringMode::Standard => "standard",
+            ScoringMode::Vegas => "Vegas",
+        };
+        let theme = if self.theme.light {"light"} else {"dark"};
+        let variant = match self.variant {
+            Variant::Klondike => "Klondike",
+            Variant::FreeCell => "FreeCell",
+            Variant::Spider => "Spider",
+        };
+        let lines = [
+            format!("Game     {variant}"),
+            format!("Draw     {}", self.game.draw_count),
+            format!("Scoring  {scoring:<8}"),
+            format!("Theme    {theme}"),
+            "Daily deal".to_string(),
+            "Deal".to_string(),
+        ];
+        let lines: Vec<String> = lines.iter().enumerate()
+            .map(|(i, line)| format!("{} {line}", if i == self.menu_row {">"} else {" "}))
+            .collect();
+        format!("Solitaire\n\n{}", lines.join("\n"))
+    }
+
+    // moves the selection to new_pos, carrying out the move if one was already selected
+    fn click(&mut self, new_pos: SelectedPos) {
+        if new_pos == SelectedPos::Stock {
+            // the stock isn't selectable, clicking it deals and picks up the new discard
+            self.selected_pos = self.click_stock();
+            return;
+        }
+        if new_pos != SelectedPos::None && new_pos == self.selected_pos {
+            // clicking the selection again cancels it
+            self.selected_pos = SelectedPos::None;
+            return;
+        }
+
+        self.try_move(self.selected_pos, new_pos);
+        self.selected_pos = self.game.selectable(new_pos);
+    }
+
+    // a refused move is flashed on its destination, and logged in verbose mode; picking another
+    // card in the same column or clicking off the board is just a change of selection
+    fn try_move(&mut self, src: SelectedPos, dest: SelectedPos) {
+        if let Err(err) = self.apply(src, dest) {
+            if self.verbose && err != MoveError::NoSelection {
+                self.log(format!("{:?} -> {:?}: {}", src, dest, err));
+            }
+            if !matches!(err, MoveError::NoSelection | MoveError::SameColumn) && dest != SelectedPos::None {
+                let reason = match err {
+                    MoveError::ColumnMismatch | MoveError::FoundationMismatch => self.refusal(src, dest),
+                    _ => None,
+                };
+                self.refused = Some((dest, reason.unwrap_or_else(|| err.to_string()), Instant::now()));
+            }
+        }
+        if self.game.check_win() {
+            self.won();
+        }
+    }
+
+    // names the card that doesn't fit and what it was played on
+    fn refusal(&self, src: SelectedPos, dest: SelectedPos) -> Option<String> {
+        let card = match src {
+            SelectedPos::Column(x, y) => self.game.rows[x].0.get(y),
+            SelectedPos::Discard => self.game.discard.0.last(),
+            SelectedPos::SuitPile(n) => self.game.suit_piles[n].0.last(),
+            SelectedPos::None | SelectedPos::Stock => None,
+        }.filter(|card| !card.hidden)?;
+        match dest {
+            SelectedPos::Column(x, _) => Some(match self.game.rows[x].0.last() {
+                Some(top) => format!("{} can't go on {}", card, top),
+                None => format!("{} can't fill an empty column", card),
+            }),
+            SelectedPos::SuitPile(n) => Some(match self.game.suit_piles[n].0.last() {
+                Some(top) => format!("{} can't go on {}", card, top),
+                None => format!("{} can't start a foundation", card),
+            }),
+            _ => None,
+        }
+    }
+
+    // plays the card at pos to whichever foundation takes it, if any, and says whether one did
+    fn send_home(&mut self, pos: SelectedPos) -> bool {
+        let card = match pos {
+            SelectedPos::Discard => self.game.discard.0.last(),
+            SelectedPos::Column(x, y) if self.game.is_bottom_card(x, y) => self.game.rows[x].0.last(),
+            _ => None,
+        };
+        let Some(n) = card.and_then(|card| self.game.foundation_for(card)) else {
+            return false;
+        };
+        if self.apply(pos, SelectedPos::SuitPile(n)).is_err() {
+            return false;
+        }
+        if self.game.check_win() {
+            self.won();
+        }
+        true
+    }
+
+    // one legal move, preferring those that reveal a card or empty a column, then foundation moves
+    fn find_hint(&self) -> Option<(SelectedPos, SelectedPos)> {
+        let rank = |&(src, dest): &(SelectedPos, SelectedPos)| match (src, dest) {
+            (SelectedPos::Column(x, y), _) if y == 0 || self.game.rows[x].0[y - 1].hidden => 2,
+            (_, SelectedPos::SuitPile(_)) => 1,
+            _ => 0,
+        };
+        self.game.legal_moves().into_iter().max_by_key(rank)
+    }
+
+    fn show_hint(&mut self) {
+        self.hint = self.find_hint();
+        if self.hint.is_none() {
+            self.notice = Some("No moves on the board, try the stock");
+        }
+    }
+
+    fn cycle_focus(&mut self, forward: bool) {
+        let Some(focus) = self.focus else {
+            self.focus = Some(Focus::REGIONS[0]);
+            return;
+        };
+        let step = if forward {1} else {Focus::REGIONS.len() - 1};
+        self.focus = Some(Focus::REGIONS[(focus.region() + step) % Focus::REGIONS.len()]);
+    }
+
+    // left/right walk the columns and on to the piles, up/down the face-up cards or the piles
+    fn move_focus(&mut self, key: KeyCode) {
+        let Some(focus) = self.focus else {
+            self.focus = Some(Focus::REGIONS[0]);
+            return;
+        };
+        self.focus = Some(match (focus, key) {
+            (Focus::Tableau(i, _), KeyCode::Left) => Focus::Tableau(i.saturating_sub(1), 0),
+            (Focus::Tableau(6, _), KeyCode::Right) => Focus::Stock,
+            (Focus::Tableau(i, _), KeyCode::Right) => Focus::Tableau(i + 1, 0),
+            (Focus::Tableau(i, depth), KeyCode::Up | KeyCode::Down) => {
+                let len = self.game.rows[self.column_order[i]].0.len();
+                // depth may have gone stale as the column changed
+                let depth = len.saturating_sub(1) - self.focus_row(i, depth);
+                if key == KeyCode::Up && self.focus_row(i, depth + 1) < self.focus_row(i, depth) {
+                    Focus::Tableau(i, depth + 1)
+                } else if key == KeyCode::Down {
+                    Focus::Tableau(i, depth.saturating_sub(1))
+                } else {
+                    Focus::Tableau(i, depth)
+                }
+            }
+            (_, KeyCode::Left) => Focus::Tableau(6, 0),
+            (pile, KeyCode::Up | KeyCode::Down) => {
+                let n = Focus::PILES.iter().position(|&p| p == pile).unwrap();
+                let n = if key == KeyCode::Up {n.saturating_sub(1)} else {(n + 1).min(5)};
+                Focus::PILES[n]
+            }
+            (focus, _) => focus,
+        });
+    }
+
+    // the card a tableau cursor rests on, never past the first face-up one
+    fn focus_row(&self, slot: usize, depth: usize) -> usize {
+        let col = &self.game.rows[self.column_order[slot]].0;
+        let first_up = col.iter().position(|c| !c.hidden).unwrap_or(0);
+        col.len().saturating_sub(1 + depth).max(first_up)
+    }
+
+    // what a click on the focused pile would pick
+    fn focus_pos(&self, focus: Focus) -> SelectedPos {
+        match focus {
+            Focus::Tableau(slot, depth) => SelectedPos::Column(self.column_order[slot], self.focus_row(slot, depth)),
+            Focus::Stock => SelectedPos::Stock,
+            Focus::Discard => {
+                if self.game.discard.0.is_empty() {
+                    return SelectedPos::None
+                }
+                SelectedPos::Discard
+            }
+            Focus::Foundation(n) => SelectedPos::SuitPile(n),
+        }
+    }
+
+    // selects the on-screen column's bottom card, or the focused one if the cursor is in it;
+    // the same digit again lets go
+    fn pick_slot(&mut self, slot: usize) {
+        let x = self.column_order[slot];
+        if matches!(self.selected_pos, SelectedPos::Column(sx, _) if sx == x) {
+            self.selected_pos = SelectedPos::None;
+            return;
+        }
+        let pos = match self.focus {
+            Some(Focus::Tableau(s, depth)) if s == slot => self.focus_pos(Focus::Tableau(s, depth)),
+            _ => SelectedPos::Column(x, self.game.rows[x].0.len().saturating_sub(1)),
+        };
+        self.click(pos);
+    }
+
+    // columns are squeezed to stay within the board's height
+    fn column_steps(&self, x: usize) -> Vec<u16> {
+        fan_steps(&self.game.rows[x].0, self.card.board_height(), self.card.height)
+    }
+
+    // board-relative row where card y of column x starts
+    fn card_y(&self, x: usize, y: usize) -> u16 {
+        self.column_steps(x).iter().take(y).sum()
+    }
+
+    // board-relative area covered by a pile
+    fn pile_rect(&self, focus: Focus) -> Rect {
+        let (w, h) = (self.card.width, self.card.height);
+        let piles_x = self.card.piles_x();
+        match focus {
+            Focus::Tableau(slot, depth) => {
+                let x = self.column_order[slot];
+                let top = self.card_y(x, self.focus_row(slot, depth));
+                let bottom = self.column_steps(x).iter().sum::<u16>() + h;
+                Rect::new(slot as u16 * w, top, w, bottom - top)
+            }
+            Focus::Stock => Rect::new(piles_x, 0, w, h),
+            Focus::Discard => Rect::new(piles_x, h, w, h),
+            Focus::Foundation(n) => Rect::new(piles_x, (2 + n as u16) * h, w, h),
+        }
+    }
+
+    // the board plus room for the discard fan in draw-three
+    fn board_width(&self) -> u16 {
+        if self.spider.is_some() {
+            return 10 * self.card.width;
+        }
+        self.card.board_width() + (self.game.draw_count as u16 - 1) * FAN_STEP
+    }
+
+    // swaps between the default cards and large ones, as long as the large board fits
+    fn toggle_large_cards(&mut self) {
+        let previous = self.card;
+        self.card = if self.card == CardSize::LARGE {CardSize::default()} else {CardSize::LARGE};
+        let (w, h) = self.min_size();
+        if self.card == CardSize::LARGE && (self.screen.width < w || self.screen.height < h) {
+            self.card = previous;
+            self.notice = Some("Not enough room for large cards");
+        }
+        self.redraw = true;
+    }
+
+    // smallest screen that fits the board, its margins and the status bar; the info panel is optional
+    fn min_size(&self) -> (u16, u16) {
+        let margin = self.theme.margin();
+        (self.board_width() + 2 * margin, self.card.board_height() + 2 * margin + 1)
+    }
+
+    // board, margins and info panel, centered on the screen when there is room to spare
+    fn layout(&self, screen: Rect) -> Rect {
+        let (min_w, height) = self.min_size();
+        let width = min_w + 1 + PANEL_W;
+        Rect::new(
+            screen.x + screen.width.saturating_sub(width) / 2,
+            screen.y + screen.height.saturating_sub(height) / 2,
+            width.min(screen.width),
+            height.min(screen.height)
+        )
+    }
+
+    // buttons sit on the bottom row of the info panel, right of the board
+    fn buttons(&self) -> Vec<(Button, Rect)> {
+        let mut x = self.board_width() + 1;
+        let y = self.card.board_height() - 1;
+        Button::ALL.iter().map(|&button| {
+            let width = button.label().chars().count() as u16;
+            let rect = Rect::new(x, y, width, 1);
+            x += width + 1;
+            (button, rect)
+        }).collect()
+    }
+
+    fn button_at(&self, x: u16, y: u16) -> Option<Button> {
+        self.buttons().into_iter()
+            .find(|(_, rect)| rect.contains(Position::new(x, y)))
+            .map(|(button, _)| button)
+    }
+
+    fn press(&mut self, button: Button) {
+        match button {
+            Button::NewGame => self.new_game(None),
+            // the seed alone rebuilds the same deal
+            // the dealt board itself, as a seed from an older version may deal another
+            Button::Restart => {
+                let Some(mut dealt) = self.dealt.clone() else {
+                    self.notice = Some("This save didn't keep its deal, so it can't restart");
+                    return;
+                };
+                dealt.draw_count = self.game.draw_count;
+                self.play(dealt);
+            }
+            Button::Undo => {
+                self.undo();
+                return;
+            }
+        }
+        self.state = AppState::Dealing(0);
+    }
+
+    fn deal_step(&mut self, shown: usize) {
+        if shown + 1 < TABLEAU_CARDS {
+            self.state = AppState::Dealing(shown + 1);
+        } else {
+            self.finish_dealing();
+        }
+    }
+
+    // the clock only starts once the whole deal is down
+    fn finish_dealing(&mut self) {
+        self.state = AppState::Playing;
+        self.start = Instant::now();
+        self.auto_advance();
+    }
+
+    // how much of column x is on the board, while the deal goes out or cards fly in
+    fn shown_len(&self, x: usize) -> usize {
+        let len = self.game.rows[x].0.len();
+        let AppState::Dealing(shown) = self.state else {
+            return len - self.flying_to(SelectedPos::Column(x, 0));
+        };
+        // card y of column x goes out on pass y, after the passes before it dealt 7, 6, ... cards
+        (0..=x).take_while(|&y| y * (15 - y) / 2 + x - y < shown).count()
+    }
+
+    // cards on their way onto the pile at pos, columns matched by x alone
+    fn flying_to(&self, pos: SelectedPos) -> usize {
+        let Some(flight) = &self.flight else {
+            return 0;
+        };
+        match (flight.dest, pos) {
+            (SelectedPos::Column(a, _), SelectedPos::Column(b, _)) if a == b => flight.count,
+            (a, b) if a == b => flight.count,
+            _ => 0,
+        }
+    }
+
+    // moves the selected column one slot over on screen, the game itself is unaffected
+    fn shift_column(&mut self, dir: isize) {
+        let SelectedPos::Column(x, _) = self.selected_pos else {
+            return;
+        };
+        let slot = self.column_order.iter().position(|&c| c == x).unwrap();
+        if let Some(other) = slot.checked_add_signed(dir).filter(|&s| s < 7) {
+            self.column_order.swap(slot, other);
+        }
+    }
+
+    fn click_stock(&mut self) -> SelectedPos {
+        if self.game.stock.0.is_empty() && !self.game.can_recycle() {
+            return SelectedPos::None;
+        }
+        self.game.deal();
+        SelectedPos::Discard
+    }
+
+    // deals one card and sends it straight to a foundation if one takes it
+    fn deal_and_play(&mut self) {
+        if self.game.stock.0.is_empty() {
+            // just turns the discard over, nothing new to play
+            self.game.deal();
+            return;
+        }
+        // one press, so one undo takes back the deal and the play
+        self.game.begin_group(false);
+        self.game.deal();
+        let card = *self.game.discard.0.last().unwrap();
+        if let Some(n) = self.game.foundation_for(&card) {
+            self.apply(SelectedPos::Discard, SelectedPos::SuitPile(n))
+                .expect("foundation_for only names a foundation that takes the card");
+        }
+        self.game.end_group();
+        if self.game.check_win() {
+            self.won();
+        }
+    }
+
+    // with nothing left to deal and every card face-up, the rest is just busywork
+    fn can_collect(&self) -> bool {
+        self.game.stock.0.is_empty() &&
+        self.game.discard.0.is_empty() &&
+        !self.game.check_win() &&
+        self.game.all_revealed()
+    }
+
+    fn offer_collect(&mut self) {
+        if !self.can_collect() {
+            return;
+        }
+        if self.auto_collect {
+            self.collect();
+        } else if !self.collect_declined && self.state == AppState::Playing {
+            self.state = AppState::CollectPrompt;
+        }
+    }
+
+    // moves cards onto the foundations until none fit
+    fn collect(&mut self) {
+        while let Some((src, dest)) = self.game.legal_moves()
+            .into_iter()
+            .find(|(_, dest)| matches!(dest, SelectedPos::SuitPile(_)))
+        {
+            if self.apply(src, dest).is_err() {
+                break;
+            }
+        }
+        if self.game.check_win() {
+            self.won();
+        }
+    }
+
+    // once every tableau card is face-up the rest can be played out, a card per step
+    fn start_autocomplete(&mut self) {
+        if self.game.check_win() || !self.game.all_revealed() {
+            return;
+        }
+        if self.confirm_autocomplete {
+            self.state = AppState::AutocompletePrompt;
+        } else {
+            self.run_autocomplete();
+        }
+    }
+
+    // everything autocomplete plays goes back with a single undo
+    fn run_autocomplete(&mut self) {
+        self.game.begin_group(false);
+        self.autocomplete = Some(0);
+    }
+
+    fn stop_autocomplete(&mut self) {
+        if self.autocomplete.take().is_some() {
+            self.game.end_group();
+        }
+    }
+
+    // plays the lowest card a foundation takes, dealing when none fits,
+    // and stops once a full pass of the stock turns up nothing
+    fn autocomplete_step(&mut self) {
+        let Some(deals) = self.autocomplete else {
+            return;
+        };
+        let rank = |pos: SelectedPos| match pos {
+            SelectedPos::Column(x, y) => self.game.rows[x].0[y].number,
+            _ => self.game.discard.0.last().map_or(0, |c| c.number),
+        };
+        let lowest = self.game.legal_moves()
+            .into_iter()
+            .filter(|(_, dest)| matches!(dest, SelectedPos::SuitPile(_)))
+            .min_by_key(|&(src, _)| rank(src));
+
+        let running = if let Some((src, dest)) = lowest {
+            self.apply(src, dest).is_ok()
+        } else if deals <= self.game.stock.0.len() + self.game.discard.0.len() {
+            self.game.deal();
+            self.autocomplete = Some(deals + 1);
+            true
+        } else {
+            false
+        };
+
+        if !running || self.game.check_win() {
+            self.stop_autocomplete();
+        } else if lowest.is_some() {
+            self.autocomplete = Some(0);
+        }
+        if self.game.check_win() {
+            self.won();
+        }
+    }
+
+    // plays a move and drops the selection, whether or not it went through
+    fn apply(&mut self, src: SelectedPos, dest: SelectedPos) -> Result<(), MoveError> {
+        self.selected_pos = SelectedPos::None;
+        let card = self.drag_run(src).first().copied();
+        let from = self.card_rect(src);
+        let mv = self.game.apply(src, dest)?;
+        self.last_move = card.map(|card| (card, dest));
+        let count = mv.cards();
+        // the moved cards now sit on top of dest
+        let landed = match dest {
+            SelectedPos::Column(x, _) => SelectedPos::Column(x, self.game.rows[x].0.len() - count),
+            pos => pos,
+        };
+        self.flight = match (from, self.card_rect(landed)) {
+            (Some(from), Some(to)) if self.move_frames > 0 => Some(Flight { dest, count, from, to, frame: 0 }),
+            _ => None,
+        };
+        Ok(())
+    }
+
+    // keeps dealing while nothing else can be played, giving up after a full pass of the stock;
+    // without auto-stock it only looks ahead for a stuck game
+    fn auto_advance(&mut self) {
+        if self.stalemate {
+            return;
+        }
+        if !self.auto_stock {
+            // the player does the dealing, so look ahead through the stock for them
+            self.stalemate = self.game.is_dead();
+            return;
+        }
+        let pass = self.game.stock.0.len() + self.game.discard.0.len() + 1;
+        // the deals are undone along with the move that called for them, and the ones
+        // before any move belong to the deal itself, so undo never lands on a board
+        // that would only deal again
+        let opening = self.game.undo_stack.is_empty();
+        self.game.begin_group(true);
+        let mut dealt = 0;
+        while self.game.legal_moves().is_empty() {
+            if dealt > pass {
+                self.stalemate = true;
+                break;
+            }
+            self.game.deal();
+            dealt += 1;
+        }
+        self.game.end_group();
+        if opening {
+            self.game.undo_stack.clear();
+        }
+    }
+
+    // maps a board-relative cell to the pile under it, without touching the game
+    fn hit_test(&self, x: usize, y: usize) -> SelectedPos {
+        let w = self.card.width as usize;
+        let h = self.card.height as usize;
+        let piles_x = self.card.piles_x() as usize;
+
+        if x < 7 * w {
+            let x = self.column_order[x / w];
+            let col = &self.game.rows[x];
+            if col.0.is_empty() {
+                return SelectedPos::Column(x, 0)
+            }
+            // the first card whose strip reaches past y, or the bottom card
+            let mut bottom = 0;
+            let y = self.column_steps(x).iter()
+                .position(|&step| {
+                    bottom += step as usize;
+                    y < bottom
+                })
+                .unwrap_or(col.0.len() - 1);
+            if col.0[y].hidden {
+                return SelectedPos::Column(x, 0)
+            }
+            return SelectedPos::Column(x, y)
+        }
+
+        // the discard fan reaches past the pile column, and any of it picks the top card
+        let fan_w = (self.game.draw_count as usize - 1) * FAN_STEP as usize;
+        if y / h == 1 && (piles_x..piles_x + w + fan_w).contains(&x) && !self.game.discard.0.is_empty() {
+            return SelectedPos::Discard;
+        }
+        if !(piles_x..piles_x + w).contains(&x) {
+            return SelectedPos::None;
+        }
+        match y / h {
+            0 => SelectedPos::Stock,
+            1 => {
+                if self.game.discard.0.is_empty() {
+                    return SelectedPos::None
+                }
+                SelectedPos::Discard
+            }
+            n @ 2..6 => {
+                SelectedPos::SuitPile(n - 2)
+            }
+            _ => {
+                SelectedPos::None
+            }
+        }
+    }
+
+    fn undo(&mut self) {
+        self.selected_pos = SelectedPos::None;
+        self.stop_autocomplete();
+        if self.game.undo_stack.is_empty() {
+            return;
+        }
+        self.game.undo();
+        self.last_move = None;
+        if self.game.scoring == ScoringMode::Standard {
+            self.game.score -= self.undo_penalty;
+        }
+    }
+
+    fn log(&mut self, line: String) {
+        if self.log.len() == LOG_LEN {
+            self.log.remove(0);
+        }
+        self.log.push(line);
+    }
+}
+
+// rows each card of a column takes before the next one starts, for all but the bottom card;
+// past the given height hidden cards lose their blank row first, then face-up ones their
+// label row, and as a last resort cards drop out from the top
+fn fan_steps(col: &[Card], height: u16, card_h: u16) -> Vec<u16> {
+    let mut steps = vec![CARD_STEP; col.len().saturating_sub(1)];
+    let mut excess = (steps.len() as u16 * CARD_STEP + card_h).saturating_sub(height);
+    for squeeze_face_up in [false, true] {
+        for (step, card) in steps.iter_mut().zip(col) {
+            if excess > 0 && *step == CARD_STEP && (card.hidden || squeeze_face_up) {
+                *step -= 1;
+                excess -= 1;
+            }
+        }
+    }
+    for step in &mut steps {
+        if excess > 0 && *step > 0 {
+            *step = 0;
+            excess -= 1;
+        }
+    }
+    steps
+}
+
+// places a board-relative rect inside the render area, clipped to it
+fn on_board(rect: Rect, area: Rect) -> Rect {
+    Rect::new(area.x + rect.x, area.y + rect.y, rect.width, rect.height).intersection(area)
+}
+
+// a pile drawn with the app's theme
+struct Themed<'a, T>(&'a T, &'a Theme);
+
+// area is the size of a single card, the column fans downward from it
+impl Widget for Themed<'_, Column> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let Themed(col, theme) = self;
+        let steps = vec![CARD_STEP; col.0.len().saturating_sub(1)];
+        Fanned(col, theme, &steps).render(area, buf);
+    }
+}
+
+// a column fanned out with the given rows per card, as laid out by fan_steps
+struct Fanned<'a>(&'a Column, &'a Theme, &'a [u16]);
+
+impl Widget for Fanned<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let Fanned(col, theme, steps) = self;
+        if col.0.is_empty() {
+            // still a place to drop a card, so it gets an outline
+            Card::block_placeholder(theme).render(area, buf);
+            return
+        }
+        let x = area.x;
+        let mut y = area.y;
+        let (w, h) = (area.width, area.height);
+        Clear.render(Rect::new(x, y, w, steps.iter().sum::<u16>() + h), buf);
+        let first = &col.0[0];
+        if col.0.len() == 1 {
+            Themed(first, theme).render(Rect::new(x, y, w, h), buf);
+            return
+        }
+        let strip = |card: &Card, block: Block, area: Rect, buf: &mut Buffer| {
+            let inner = block.inner(area);
+            Paragraph::new(theme.card_span(card))
+                .alignment(theme.align)
+                .block(block)
+                .render(area, buf);
+            render_back(card, theme, inner, buf);
+        };
+        strip(first, Card::block_first(theme), Rect::new(x, y, w, steps[0]), buf);
+        y += steps[0];
+        for (card, &step) in col.0[1..col.0.len() - 1].iter().zip(&steps[1..]) {
+            strip(card, Card::block_middle(theme), Rect::new(x, y, w, step), buf);
+            y += step;
+        }
+
+        let last = col.0.last().unwrap();
+        strip(last, Card::block_last(theme), Rect::new(x, y, w, h), buf);
+        render_corner(last, theme, Rect::new(x, y, w, h), buf);
+    }
+}
+
+// the pattern on the back of a face-down card, inside its border
+fn render_back(card: &Card, theme: &Theme, inner: Rect, buf: &mut Buffer) {
+    if !card.hidden {
+        return;
+    }
+    let back = if theme.ascii && !theme.card_back.is_ascii() {'#'} else {theme.card_back};
+    for y in inner.top()..inner.bottom() {
+        for x in inner.left()..inner.right() {
+            buf[(x, y)].set_char(back).set_style(theme.back_style);
+        }
+    }
+}
+
+// the label repeated in the bottom-right of a fully shown card, when it is tall enough to
+// have a row of its own for it
+fn render_corner(card: &Card, theme: &Theme, area: Rect, buf: &mut Buffer) {
+    if card.hidden || area.height < 4 || area.width < 3 {
+        return;
+    }
+    Paragraph::new(theme.card_span(card))
+        .right_aligned()
+        .render(Rect::new(area.x + 1, area.bottom() - 2, area.width - 2, 1), buf);
+    render_pips(card, theme, area, buf);
+}
+
+// pips per row down the middle of a large card, for A to 10
+const PIP_ROWS: [[usize; 3]; 10] = [
+    [0, 1, 0], [1, 0, 1], [1, 1, 1], [2, 0, 2], [2, 1, 2],
+    [2, 2, 2], [3, 1, 3], [3, 2, 3], [3, 3, 3], [4, 2, 4],
+];
+
+// number cards get their pips and face cards their letter, once the card is large enough
+fn render_pips(card: &Card, theme: &Theme, area: Rect, buf: &mut Buffer) {
+    if area.width < CardSize::LARGE.width || area.height < CardSize::LARGE.height {
+        return;
+    }
+    let suit = if theme.ascii {Card::SUIT_LETTERS} else {Card::SUITS}[card.suit as usize];
+    let rows = match PIP_ROWS.get(card.number as usize) {
+        Some(counts) => counts.map(|n| vec![suit; n].join(" ")),
+        None => [String::new(), Card::NUMBERS[card.number as usize].to_string(), String::new()],
+    };
+    let top = area.y + area.height / 2 - 1;
+    for (row, y) in rows.into_iter().zip(top..) {
+        Paragraph::new(Span::styled(row, theme.card_style(card)))
+            .centered()
+            .render(Rect::new(area.x + 1, y, area.width - 2, 1), buf);
+    }
+}
+
+impl Widget for Themed<'_, Card> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let Themed(card, theme) = self;
+        Clear.render(area, buf);
+        Paragraph::new(theme.card_span(card))
+            .alignment(theme.align)
+            .block(Card::block_single(theme))
+            .render(area, buf);
+        render_back(card, theme, Card::block_single(theme).inner(area), buf);
+        render_corner(card, theme, area, buf);
+    }
+}
+
+impl Widget for Themed<'_, Pile> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let Themed(pile, theme) = self;
+        if let Some(top) = pile.0.last() {
+            Themed(top, theme).render(area, buf);
+            return
+        }
+        Clear.render(area, buf);
+        Card::BLOCK_EMPTY.border_style(theme.empty).render(area, buf);
+    }
+}
+
+impl Widget for &App {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        // terminals can briefly report an empty area while resizing
+        if area.width == 0 || area.height == 0 {
+            return;
+        }
+
+        let (w, h) = (self.card.width, self.card.height);
+        let margin = self.theme.margin();
+        let (board_w, board_h) = (self.board_width(), self.card.board_height());
+        let (min_w, min_h) = self.min_size();
+        if area.width < min_w || area.height < min_h {
+            Paragraph::new(format!("Too small\nneed {min_w}x{min_h}, have {}x{}", area.width, area.height))
+                .render(area, buf);
+            return;
+        }
+
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                buf[(x, y)]
+                    .set_char(self.theme.background)
+                    .set_style(self.theme.background_style);
+            }
+        }
+
+        let area = self.layout(area);
+
+        if matches!(self.state, AppState::Menu | AppState::ResumePrompt) {
+            Span::styled("up/down choose  left/right change  Enter deal  Esc quit", Style::new().dim())
+                .render(Rect::new(area.x, area.bottom() - 1, area.width, 1), buf);
+            let area = Rect::new(area.x + margin, area.y + margin, area.width - margin, area.height - margin);
+            self.render_popup(&self.menu(), Alignment::Left, area, buf);
+            if self.state == AppState::ResumePrompt {
+                self.render_popup("Resume the saved game? (y/n)", Alignment::Center, area, buf);
+            }
+            return;
+        }
+
+        if let Some(fc) = &self.freecell {
+            self.render_freecell(fc, area, buf);
+            return;
+        }
+        if let Some(sp) = &self.spider {
+            self.render_spider(sp, area, buf);
+            return;
+        }
+
+        Span::styled(self.status_bar(), Style::new().dim())
+            .render(Rect::new(area.x, area.bottom() - 1, area.width, 1), buf);
+
+        // everything else is laid out inside the frame
+        if self.theme.frame {
+            Block::bordered()
+                .border_set(self.theme.border())
+                .border_style(self.theme.frame_style)
+                .title(format!(" Klondike — Seed {} ", self.game.seed))
+                .render(Rect::new(area.x, area.y, board_w + 2, board_h + 2), buf);
+        }
+        let area = Rect::new(area.x + margin, area.y + margin, area.width - margin, area.height - margin);
+
+        if self.separators {
+            self.render_separators(area, buf);
+        }
+
+        let mut x = area.x;
+        let mut y = area.y;
+
+        // columns
+        for &i in &self.column_order {
+            let dealt = Column(self.game.rows[i].0[..self.shown_len(i)].to_vec());
+            Fanned(&dealt, &self.theme, &self.column_steps(i)).render(Rect::new(
+                x,
+                y,
+                w,
+                h
+            ), buf);
+            x += w;
+        }
+
+        x += 1;
+        // stock, with the cards left to deal on its bottom edge
+        if self.game.can_recycle() {
+            let recycle = if self.theme.ascii {"R"} else {"↻"};
+            Clear.render(Rect::new(x, y, w, h), buf);
+            Paragraph::new(Span::styled(recycle, self.theme.empty))
+                .alignment(self.theme.align)
+                .block(Card::block_single(&self.theme))
+                .render(Rect::new(x, y, w, h), buf);
+        } else {
+            Themed(&self.game.stock, &self.theme).render(Rect::new(
+                x,
+                y,
+                w,
+                h
+            ), buf);
+        }
+        if self.peek_stock && self.game.draw_count == 1 {
+            if let Some(top) = self.game.stock.0.last() {
+                // shown face up, so without the card back
+                Clear.render(Rect::new(x, y, w, h), buf);
+                Paragraph::new(self.theme.preview_span(top))
+                    .alignment(self.theme.align)
+                    .block(Card::block_single(&self.theme))
+                    .render(Rect::new(x, y, w, h), buf);
+            }
+        }
+        if !self.game.stock.0.is_empty() {
+            Paragraph::new(self.game.stock.0.len().to_string())
+                .centered()
+                .render(Rect::new(x + 1, y + h - 1, w - 2, 1), buf);
+        }
+        // redeals left go on the top edge, when they are limited
+        if let Some(n) = self.game.redeals_remaining {
+            let recycle = if self.theme.ascii {"R"} else {"↻"};
+            Paragraph::new(format!("{recycle}{n}"))
+                .centered()
+                .render(Rect::new(x + 1, y, w - 2, 1), buf);
+        }
+        y += h;
+
+        // discard, the last few fanned out to the right in draw-three
+        let fan = &self.game.discard.0[self.game.discard.0.len().saturating_sub(self.game.draw_count as usize)..];
+        if fan.is_empty() {
+            Themed(&self.game.discard, &self.theme).render(Rect::new(x, y, w, h), buf);
+        }
+        for (i, card) in fan.iter().enumerate() {
+            Themed(card, &self.theme).render(Rect::new(x + i as u16 * FAN_STEP, y, w, h), buf);
+        }
+        y += h;
+
+        // suit piles, each marked with its suit while empty
+        for i in 0..4 {
+            let pile = &self.game.suit_piles[i].0;
+            let gone = self.cascade.as_ref().map_or(0, |c| c.taken(i)) + self.flying_to(SelectedPos::SuitPile(i));
+            Themed(&Pile(pile[..pile.len() - gone].to_vec()), &self.theme).render(Rect::new(
+                x,
+                y,
+                w,
+                h
+            ), buf);
+            if self.game.suit_piles[i].0.is_empty() {
+                self.render_suit_mark(i, Rect::new(x, y, w, h), buf);
+            }
+            y += h;
+        }
+
+        if self.foundation_hints {
+            self.render_foundation_hints(area, buf);
+        }
+
+        if let Some((src, dest)) = self.hint {
+            for rect in [src, dest].into_iter().filter_map(|pos| self.label_rect(pos)) {
+                buf.set_style(on_board(rect, area), self.theme.selected);
+            }
+        }
+
+        for dest in self.game.legal_destinations(self.selected_pos) {
+            let focus = match dest {
+                SelectedPos::Column(x, _) => {
+                    let slot = self.column_order.iter().position(|&c| c == x).unwrap();
+                    Focus::Tableau(slot, 0)
+                }
+                SelectedPos::SuitPile(n) => Focus::Foundation(n),
+                _ => continue,
+            };
+            Block::bordered()
+                .border_set(self.theme.border())
+                .border_style(self.theme.target)
+                .render(on_board(self.pile_rect(focus), area), buf);
+        }
+
+        if let Some(focus) = self.focus {
+            let rect = self.pile_rect(focus);
+            let rect = on_board(rect, area);
+            Block::bordered()
+                .border_set(self.theme.border())
+                .border_style(self.theme.focus)
+                .render(rect, buf);
+        }
+
+        for (button, rect) in self.buttons() {
+            let rect = on_board(rect, area);
+            Span::styled(button.label(), Style::new().black().on_white()).render(rect, buf);
+        }
+
+        let panel_w = area.width.saturating_sub(board_w + 1);
+        Span::raw(format!("Time {}", self.clock()))
+            .render(Rect::new(area.x + board_w + 1, area.y, panel_w, 1), buf);
+        // enough to replay or share the deal with --seed
+        Span::raw(self.seed_label(self.game.seed))
+            .render(Rect::new(area.x + board_w + 1, area.y + 1, panel_w, 1), buf);
+        Span::raw(format!("Moves {}", self.game.moves))
+            .render(Rect::new(area.x + board_w + 1, area.y + 2, panel_w, 1), buf);
+        let score = match (self.scored(), self.game.scoring) {
+            (false, _) if self.unscored => "Score --".to_string(),
+            (false, _) => "Score -- (peek)".to_string(),
+            (true, ScoringMode::Standard) => format!("Score {}", self.game.score),
+            (true, ScoringMode::Vegas) => {
+                format!("Score ${}  session ${}", self.game.score, self.session_score + self.game.score)
+            }
+        };
+        Span::raw(score).render(Rect::new(area.x + board_w + 1, area.y + 3, panel_w, 1), buf);
+        Span::raw(format!("Stock {}", self.game.stock.0.len()))
+            .render(Rect::new(area.x + board_w + 1, area.y + 4, panel_w, 1), buf);
+        if let Some((card, dest)) = self.last_move {
+            let to = match dest {
+                SelectedPos::Column(x, _) => format!("column {}", self.column_order.iter().position(|&c| c == x).unwrap() + 1),
+                _ => "foundation".to_string(),
+            };
+            Span::raw(format!("Last {card} to {to}"))
+                .render(Rect::new(area.x + board_w + 1, area.y + 5, panel_w, 1), buf);
+        }
+
+        let notice_area = Rect::new(area.x + board_w + 1, area.y + self.card.board_height() - 3, panel_w, 1);
+        if let Some((dest, reason, _)) = &self.refused {
+            let focus = match *dest {
+                SelectedPos::Column(x, _) => self.column_order.iter().position(|&c| c == x).map(|slot| Focus::Tableau(slot, 0)),
+                SelectedPos::SuitPile(n) => Some(Focus::Foundation(n)),
+                SelectedPos::Discard => Some(Focus::Discard),
+                SelectedPos::None | SelectedPos::Stock => None,
+            };
+            if let Some(focus) = focus {
+                Block::bordered()
+                    .border_set(self.theme.border())
+                    .border_style(self.theme.refused)
+                    .render(on_board(self.pile_rect(focus), area), buf);
+            }
+            Span::styled(reason.as_str(), self.theme.refused).render(notice_area, buf);
+        } else if let Some(notice) = self.notice {
+            Span::styled(notice, Style::new().yellow()).render(notice_area, buf);
+        }
+
+        if self.verbose {
+            // stops short of the notice row on small cards
+            let log_h = (LOG_LEN as u16).min(board_h - 9);
+            let log_area = Rect::new(area.x + board_w + 1, area.y + 6, panel_w, log_h);
+            Paragraph::new(self.log.join("\n")).render(log_area, buf);
+        }
+
+        if let Some(drag) = self.drag.filter(|d| d.at != d.from) {
+            self.render_drag(drag, area, buf);
+        }
+
+        if let Some(flight) = &self.flight {
+            self.render_flight(flight, area, buf);
+        }
+
+        self.render_cascade(area, buf);
+
+        if self.stalemate {
+            let text = format!(
+                "No moves left\n{} new game  r restart  {} quit",
+                self.keymap.label(Action::NewGame), self.keymap.label(Action::Quit)
+            );
+            self.render_popup(&text, Alignment::Center, area, buf);
+        }
+
+        if self.state == AppState::CollectPrompt {
+            self.render_popup("Collect to foundations? (y/n)", Alignment::Center, area, buf);
+        }
+        if self.state == AppState::AutocompletePrompt {
+            self.render_popup("Autocomplete the game? (y/n)", Alignment::Center, area, buf);
+        }
+
+        if self.show_stats {
+            self.render_popup(&self.stats.summary(), Alignment::Left, area, buf);
+        }
+
+        match self.state {
+            AppState::ConfirmQuit => {
+                let text = if self.save_file.is_some() {"Quit? The game will be saved (y/n)"} else {"Quit? (y/n)"};
+                self.render_popup(text, Alignment::Center, area, buf);
+            }
+            AppState::Help => self.render_popup(&self.help(), Alignment::Left, area, buf),
+            AppState::SeedPrompt => self.render_popup(&self.seed_prompt(), Alignment::Left, area, buf),
+            _ => {}
+        }
+
+        if self.debug_hitboxes {
+            self.render_hitboxes(area, buf);
+        }
+    }
+}
+
+impl App {
+    // an empty foundation shows the suit it is for
+    fn render_suit_mark(&self, n: usize, area: Rect, buf: &mut Buffer) {
+        let suit = if self.theme.ascii {Card::SUIT_LETTERS[n]} else {Card::SUITS[n]};
+        Paragraph::new(Span::styled(suit, self.theme.empty))
+            .centered()
+            .render(Rect::new(area.x + 1, area.y + area.height / 2, area.width - 2, 1), buf);
+    }
+
+    // text boxed and centered over the board, with its lines aligned within the box
+    fn render_popup(&self, text: &str, alignment: Alignment, area: Rect, buf: &mut Buffer) {
+        let width = text.lines().map(|l| l.chars().count()).max().unwrap_or(0) as u16 + 4;
+        let height = text.lines().count() as u16 + 2;
+        let popup = Rect::new(
+            area.x + self.board_width().saturating_sub(width) / 2,
+            area.y + (self.card.board_height() / 2).saturating_sub(height / 2 + 1),
+            width,
+            height
+        ).intersection(area);
+        Clear.render(popup, buf);
+        Paragraph::new(text)
+            .alignment(alignment)
+            .block(Card::block_single(&self.theme).padding(Padding::horizontal(1)))
+            .render(popup, buf);
+    }
+
+    // the dragged cards, offset from their pile by however far the cursor has moved
+    fn render_drag(&self, drag: Drag, area: Rect, buf: &mut Buffer) {
+        let Some(label) = self.label_rect(drag.src) else {
+            return;
+        };
+        let (w, h) = (self.card.width, self.card.height);
+        // kept on screen, losing cards off the top of a long run if it has to
+        let fit = (area.height.saturating_sub(h) / CARD_STEP + 1) as usize;
+        let run = self.drag_run(drag.src);
+        let run = &run[run.len().saturating_sub(fit)..];
+        if run.is_empty() || area.height < h {
+            return;
+        }
+        let run_h = (run.len() as u16 - 1) * CARD_STEP + h;
+        let x = (label.x - 1 + drag.at.0).saturating_sub(drag.from.0).min(area.width.saturating_sub(w));
+        let y = (label.y - 1 + drag.at.1).saturating_sub(drag.from.1).min(area.height - run_h);
+        Themed(&Column(run.to_vec()), &self.theme).render(Rect::new(area.x + x, area.y + y, w, h), buf);
+    }
+
+    // board-relative area of the card at pos, for the piles the top card
+    fn card_rect(&self, pos: SelectedPos) -> Option<Rect> {
+        let (w, h) = (self.card.width, self.card.height);
+        let piles_x = self.card.piles_x();
+        let (x, y) = match pos {
+            SelectedPos::None => return None,
+            SelectedPos::Column(x, y) => {
+                let slot = self.column_order.iter().position(|&c| c == x)? as u16;
+                (slot * w, self.card_y(x, y))
+            }
+            SelectedPos::Stock => (piles_x, 0),
+            SelectedPos::Discard => {
+                let fanned = self.game.discard.0.len().min(self.game.draw_count as usize);
+                (piles_x + fanned.saturating_sub(1) as u16 * FAN_STEP, h)
+            }
+            SelectedPos::SuitPile(n) => (piles_x, (2 + n as u16) * h),
+        };
+        Some(Rect::new(x, y, w, h))
+    }
+
+    // board-relative row holding a card's label
+    fn label_rect(&self, pos: SelectedPos) -> Option<Rect> {
+        let card = self.card_rect(pos)?;
+        Some(Rect::new(card.x + 1, card.y + 1, card.width - 2, 1))
+    }
+
+    // the moved cards partway along the line between their old and new places
+    fn render_flight(&self, flight: &Flight, area: Rect, buf: &mut Buffer) {
+        let cards = match flight.dest {
+            SelectedPos::Column(x, _) => &self.game.rows[x].0,
+            SelectedPos::SuitPile(n) => &self.game.suit_piles[n].0,
+            _ => return,
+        };
+        let run = &cards[cards.len() - flight.count..];
+        let t = f32::from(flight.frame) / f32::from(self.move_frames);
+        let along = |a: u16, b: u16| (f32::from(a) + (f32::from(b) - f32::from(a)) * t).round() as u16;
+        let (x, y) = (along(flight.from.x, flight.to.x), along(flight.from.y, flight.to.y));
+        // the whole run has to fit below where it is drawn
+        let run_h = (run.len() as u16 - 1) * CARD_STEP + self.card.height;
+        let y = y.min(area.height.saturating_sub(run_h));
+        Themed(&Column(run.to_vec()), &self.theme)
+            .render(on_board(Rect::new(x, y, flight.to.width, flight.to.height), area), buf);
+    }
+
+    // every spot a cascading card has bounced through
+    fn render_cascade(&self, area: Rect, buf: &mut Buffer) {
+        let Some(cascade) = &self.cascade else {
+            return;
+        };
+        for &(x, y, card) in &cascade.trail {
+            Themed(&card, &self.theme).render(on_board(Rect::new(x, y, self.card.width, self.card.height), area), buf);
+        }
+    }
+
+    // marks exposed cards that one of the foundations is waiting for
+    fn render_foundation_hints(&self, area: Rect, buf: &mut Buffer) {
+        let needs = self.game.next_foundation_needs();
+        let wanted = |card: &Card| needs.contains(&Some((card.suit, card.number)));
+
+        let mut exposed: Vec<SelectedPos> = self.game.rows.iter().enumerate()
+            .filter(|(_, col)| col.0.last().is_some_and(wanted))
+            .map(|(x, col)| SelectedPos::Column(x, col.0.len() - 1))
+            .collect();
+        if self.game.discard.0.last().is_some_and(wanted) {
+            exposed.push(SelectedPos::Discard);
+        }
+
+        for pos in exposed {
+            if let Some(rect) = self.label_rect(pos) {
+                let rect = on_board(rect, area);
+                buf.set_style(rect, self.theme.hint);
+            }
+        }
+    }
+
+    // vertical rules in the gap after the tableau and along the info panel's edge
+    fn render_separators(&self, area: Rect, buf: &mut Buffer) {
+        let height = self.card.board_height().min(area.height);
+        for x in [self.card.piles_x() - 1, self.board_width()] {
+            if x >= area.width {
+                continue;
+            }
+            for y in 0..height {
+                buf[(area.x + x, area.y + y)]
+                    .set_symbol(symbols::line::VERTICAL)
+                    .set_style(self.theme.separator);
+            }
+        }
+    }
+
+    // tints the regions matched in hit_test
+    fn render_hitboxes(&self, area: Rect, buf: &mut Buffer) {
+        let tint = |x: u16, y: u16, w: u16, h: u16, color: Color, buf: &mut Buffer| {
+            let rect = on_board(Rect::new(x, y, w, h), area);
+            buf.set_style(rect, Style::new().bg(color));
+        };
+
+        let (w, h) = (self.card.width, self.card.height);
+        let piles_x = self.card.piles_x();
+
+        // columns take the full height
+        for i in 0..7 {
+            let color = if i % 2 == 0 {Color::Blue} else {Color::Cyan};
+            tint(i * w, 0, w, area.height, color, buf);
+        }
+
+        // stock
+        tint(piles_x, 0, w, h, Color::Green, buf);
+
+        // discard
+        tint(piles_x, h, self.board_width() - piles_x, h, Color::Yellow, buf);
+
+        // suit piles
+        for i in 0..4 {
+            let color = if i % 2 == 0 {Color::Magenta} else {Color::Red};
+            tint(piles_x, (2 + i) * h, w, h, color, buf);
+        }
+
+        Span::raw(format!("hash {:016x}", self.game.state_hash()))
+            .render(Rect::new(area.x, area.bottom() - 1, area.width, 1), buf);
+    }
+}
+
+// past this many moves a game is assumed to be going in circles
+const STRESS_STEP_LIMIT: usize = 5000;
+
+// plays random legal moves, dealing through dead positions, and checks the board after each
+fn stress(games: u64) {
+    let (mut won, mut stuck, mut gave_up) = (0, 0, 0);
+    for seed in 0..games {
+        let mut engine = Engine::new(seed, 1);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut steps = 0;
+        // deals in a row with nothing to play, a full pass of the stock means the game is stuck
+        let mut dealt = 0;
+        loop {
+            engine.state().assert_invariants();
+            if engine.is_won() {
+                won += 1;
+                break;
+            }
+            let moves: Vec<engine::Action> = engine.legal_actions()
+                .into_iter()
+                .filter(|&action| action != engine::Action::Deal)
+                .collect();
+            if moves.is_empty() {
+                let pass = engine.state().stock.0.len() + engine.state().discard.0.len() + 1;
+                if dealt > pass || engine.apply(engine::Action::Deal).is_err() {
+                    stuck += 1;
+                    break;
+                }
+                dealt += 1;
+                continue;
+            }
+            dealt = 0;
+            if steps == STRESS_STEP_LIMIT {
+                gave_up += 1;
+                break;
+            }
+            let action = *moves.choose(&mut rng).unwrap();
+            // every move must undo back to the same board, and go through again after
+            let before = engine.state().state_hash();
+            for step in [action, engine::Action::Undo] {
+                if let Err(err) = engine.apply(step) {
+                    panic!("seed {seed}: {step:?} for legal move {action:?} rejected: {err}");
+                }
+            }
+            assert_eq!(engine.state().state_hash(), before, "seed {seed}: undo of {action:?} changed the board");
+            engine.apply(action).unwrap();
+            steps += 1;
+        }
+    }
+    println!("games: {games}  won: {won}  stalemate: {stuck}  step limit: {gave_up}");
+}
+
+fn main() -> Result<(), Error> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let daily = args.iter().any(|a| a == "--daily").then(daily_seed);
+    let seed = args.iter()
+        .position(|a| a == "--seed")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|s| s.parse().ok())
+        .or(daily);
+    let mut app = App::init(seed);
+    app.daily = daily;
+    if let Some(path) = KeyMap::path() {
+        app.keymap = KeyMap::load(&path);
+    }
+    app.stats_file = Stats::path();
+    if let Some(path) = &app.stats_file {
+        app.stats = Stats::load(path);
+    }
+    // options given on the command line win over the remembered ones
+    app.prefs_file = Prefs::path();
+    if let Some(path) = &app.prefs_file {
+        app.saved_prefs = Prefs::load(path);
+        app.apply_prefs(app.saved_prefs);
+    }
+    let mut print_deal = false;
+    let mut stress_games = None;
+    let mut reset_stats = false;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            "--seed" => {arg_value::<u64>(&mut args, &arg, |s| s.parse().ok());}
+            "--daily" => {}
+            "--print-deal" => print_deal = true,
+            "--reset-stats" => reset_stats = true,
+            "--stress" => stress_games = Some(arg_value(&mut args, &arg, |n| n.parse().ok())),
+            "--debug-hitboxes" => app.debug_hitboxes = true,
+            "--peek" => app.peek_stock = true,
+            "--draw-three" => app.game.draw_count = 3,
+            "--draw" => app.game.draw_count = arg_value(&mut args, &arg, |n| n.parse().ok().filter(|n| [1, 3].contains(n))),
+            "--vegas" => {
+                app.scoring = ScoringMode::Vegas;
+                app.game.set_scoring(ScoringMode::Vegas);
+            }
+            "--scoring" => {
+                let (scoring, unscored) = arg_value(&mut args, &arg, |mode| match mode {
+                    "standard" => Some((ScoringMode::Standard, false)),
+                    "vegas" => Some((ScoringMode::Vegas, false)),
+                    "none" => Some((ScoringMode::Standard, true)),
+                    _ => None,
+                });
+                app.scoring = scoring;
+                app.game.set_scoring(scoring);
+                app.unscored = unscored;
+            }
+            "--redeals" => {
+                app.redeal_limit = Some(arg_value(&mut args, &arg, |n| n.parse().ok()));
+                app.game.redeals_remaining = app.redeal_limit;
+            }
+            "--auto-stock" => app.auto_stock = true,
+            "--undo-penalty" => app.undo_penalty = arg_value(&mut args, &arg, |n| n.parse().ok()),
+            "--undo-limit" => {
+                app.undo_limit = arg_value(&mut args, &arg, |n| n.parse().ok().filter(|&n| n > 0));
+                app.game.undo_limit = app.undo_limit;
+            }
+            "--freecell" => app.variant = Variant::FreeCell,
+            "--spider" => app.variant = Variant::Spider,
+            "--move-speed" => {
+                app.move_frames = arg_value(&mut args, &arg, |speed| match speed {
+                    "off" => Some(0),
+                    "fast" => Some(MOVE_FRAMES_NORMAL / 2),
+                    "normal" => Some(MOVE_FRAMES_NORMAL),
+                    "slow" => Some(MOVE_FRAMES_NORMAL * 2),
+                    _ => None,
+                });
+            }
+            "--click" => {
+                app.click_mode = arg_value(&mut args, &arg, |mode| match mode {
+                    "select" => Some(ClickMode::Select),
+                    "place" => Some(ClickMode::Place),
+                    _ => None,
+                });
+            }
+            "--verbose" => app.verbose = true,
+            "--auto-collect" => app.auto_collect = true,
+            "--confirm-autocomplete" => app.confirm_autocomplete = true,
+            "--separators" => app.separators = true,
+            "--unicode-cards" => app.theme.card_glyphs = true,
+            "--ascii" => app.theme.ascii = true,
+            "--four-color" => app.theme.four_color = true,
+            "--light" => app.theme.toggle_light(),
+            "--theme" => {
+                let light = arg_value(&mut args, &arg, |theme| match theme {
+                    "dark" => Some(false),
+                    "light" => Some(true),
+                    _ => None,
+                });
+                if light != app.theme.light {
+                    app.theme.toggle_light();
+                }
+            }
+            "--centiseconds" => app.centiseconds = true,
+            "--foundation-hints" => app.foundation_hints = true,
+            "--frame" => app.theme.frame = true,
+            "--align" => {
+                app.theme.align = arg_value(&mut args, &arg, |align| match align {
+                    "left" => Some(Alignment::Left),
+                    "center" => Some(Alignment::Center),
+                    "right" => Some(Alignment::Right),
+                    _ => None,
+                });
+            }
+            "--large-cards" => app.card = CardSize::LARGE,
+            "--card-size" => app.card = arg_value(&mut args, &arg, CardSize::parse),
+            "--felt" => app.theme.background_style = Style::new().on_green(),
+            "--card-back" => app.theme.card_back = arg_value(&mut args, &arg, |c| c.chars().next()),
+            "--background" => app.theme.background = arg_value(&mut args, &arg, |c| c.chars().next()),
+            _ => bad_arg(&format!("unknown option {arg}")),
+        }
+    }
+
+    if reset_stats {
+        if let Some(path) = &app.stats_file {
+            Stats::default().save(path)?;
+        }
+        println!("Stats reset");
+        return Ok(());
+    }
+
+    if let Some(games) = stress_games {
+        stress(games);
+        return Ok(());
+    }
+
+    if print_deal {
+        print!("{}", app.game.to_ascii_board());
+        return Ok(());
+    }
+
+    // an explicit --seed asks for that deal, so only offer the save otherwise
+    app.save_file = App::save_path();
+    // a deal asked for by seed skips straight to it
+    if seed.is_some() {
+        app.start(seed);
+    } else if app.save_file.as_deref().and_then(App::saved_game).is_some() {
+        app.state = AppState::ResumePrompt;
+    } else {
+        app.state = AppState::Menu;
+    }
+
+    let mut terminal = ratatui::init();
+    let _guard = TerminalGuard;
+    execute!(io::stdout(), EnableMouseCapture)?;
+    app.run(&mut terminal)
+}
+
+
+// the other variants lay out a row of piles across the top with the columns below it
+impl App {
+    // where a mouse event lands, relative to the board
+    fn board_point(&self, ev: event::MouseEvent) -> Option<(u16, u16)> {
+        let margin = self.theme.margin();
+        let board = self.layout(self.screen);
+        Some((ev.column.checked_sub(board.x + margin)?, ev.row.checked_sub(board.y + margin)?))
+    }
+
+    // a column starting a row below the top piles, sharing the rest of the board's height
+    fn lower_steps(&self, col: &[Card]) -> Vec<u16> {
+        let h = self.card.height;
+        fan_steps(col, self.card.board_height() - h - 1, h)
+    }
+
+    fn lower_card_y(&self, col: &[Card], y: usize) -> u16 {
+        self.card.height + 1 + self.lower_steps(col).iter().take(y).sum::<u16>()
+    }
+
+    // the card of a lower column whose strip (or whole face, for the last) covers board row y;
+    // an empty column answers 0 anywhere over its slot
+    fn lower_card_at(&self, col: &[Card], y: u16) -> Option<usize> {
+        let h = self.card.height;
+        let mut top = h + 1;
+        if y < top {
+            return None;
+        }
+        for (i, step) in self.lower_steps(col).into_iter().enumerate() {
+            if y < top + step {
+                return Some(i);
+            }
+            top += step;
+        }
+        (y < top + h).then_some(col.len().saturating_sub(1))
+    }
+
+    // status bar under the board, then the board area with the info panel lines beside it
+    fn render_side(&self, status: String, panel: &[String], area: Rect, buf: &mut Buffer) -> Rect {
+        Span::styled(status, Style::new().dim())
+            .render(Rect::new(area.x, area.bottom() - 1, area.width, 1), buf);
+        let margin = self.theme.margin();
+        let area = Rect::new(area.x + margin, area.y + margin, area.width - margin, area.height - margin);
+        let board_w = self.board_width();
+        for (row, line) in (0..).zip(panel) {
+            Span::raw(line).render(Rect::new(area.x + board_w + 1, area.y + row, area.width.saturating_sub(board_w + 1), 1), buf);
+        }
+        area
+    }
+}
+
+// FreeCell: cells then foundations across the top, the eight columns below them
+impl App {
+    fn new_freecell(&mut self, seed: Option<u64>) {
+        self.end_game();
+        self.freecell = Some(FreeCell::new(seed.unwrap_or_else(rand::random)));
+        self.freecell_selected = None;
+        self.freecell_last_click = None;
+        self.start = Instant::now();
+        self.recorded = false;
+    }
+
+    fn freecell_key(&mut self, code: KeyCode) {
+        match self.keymap.action(code) {
+            Some(Action::Undo) => {
+                self.freecell_selected = None;
+                if let Some(fc) = &mut self.freecell {
+                    fc.undo();
+                }
+            }
+            Some(Action::NewGame) => self.new_freecell(None),
+            Some(Action::ClearSelection) => self.freecell_selected = None,
+            Some(Action::Quit) => self.quit(),
+            _ => {}
+        }
+    }
+
+    // acts on the release like the Klondike board does, so a press can still become a drag
+    fn freecell_mouse(&mut self, ev: event::MouseEvent) {
+        let Some((x, y)) = self.board_point(ev) else {
+            self.pressed = None;
+            return;
+        };
+        match ev.kind {
+            MouseEventKind::Down(event::MouseButton::Left) => self.pressed = Some((x, y)),
+            MouseEventKind::Up(event::MouseButton::Left) => self.freecell_release(x, y),
+            MouseEventKind::Up(event::MouseButton::Right) => {
+                if let Some(spot) = self.freecell_spot(x, y) {
+                    self.freecell_home(spot);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // a drag that ends on another pile is a move, anything else counts as a click
+    fn freecell_release(&mut self, x: u16, y: u16) {
+        let pressed = self.pressed.take();
+        let Some(spot) = self.freecell_spot(x, y) else {
+            return;
+        };
+        let pile = |spot: Spot| match spot {
+            Spot::Column(x, _) => Spot::Column(x, 0),
+            spot => spot,
+        };
+        if let Some(src) = pressed.filter(|&at| at != (x, y)).and_then(|(x, y)| self.freecell_spot(x, y)) {
+            if pile(src) != pile(spot) {
+                self.freecell_last_click = None;
+                self.freecell_selected = None;
+                self.freecell_move(src, spot);
+                return;
+            }
+        }
+
+        let now = Instant::now();
+        let double = self.freecell_last_click.is_some_and(|(at, last)| last == spot && now - at < DOUBLE_CLICK);
+        if double {
+            self.freecell_last_click = None;
+            // a card with nowhere to go is still the selection, and a second click drops it
+            if !self.freecell_home(spot) && self.freecell_selected == Some(spot) {
+                self.freecell_click(spot);
+            }
+            return;
+        }
+        self.freecell_last_click = Some((now, spot));
+        self.freecell_click(spot);
+    }
+
+    // picks up the cards at spot, or puts down the ones already picked up
+    fn freecell_click(&mut self, spot: Spot) {
+        match self.freecell_selected.take() {
+            // clicking the selection again lets go of it
+            Some(src) if src == spot => {}
+            Some(src) => {
+                if !self.freecell_move(src, spot) {
+                    self.freecell_selected = Some(spot);
+                }
+            }
+            None => self.freecell_selected = Some(spot),
+        }
+        if let Some(fc) = &self.freecell {
+            self.freecell_selected = self.freecell_selected.filter(|&s| !fc.run(s).is_empty());
+        }
+    }
+
+    fn freecell_move(&mut self, src: Spot, dst: Spot) -> bool {
+        let Some(fc) = &mut self.freecell else {
+            return false;
+        };
+        if let Err(err) = fc.apply(src, dst) {
+            if self.verbose {
+                self.log(format!("{:?} -> {:?}: {}", src, dst, err));
+            }
+            return false;
+        }
+        if fc.check_win() {
+            self.won();
+        }
+        true
+    }
+
+    // the top card at spot straight up to its foundation, if it fits
+    fn freecell_home(&mut self, spot: Spot) -> bool {
+        let Some(fc) = &self.freecell else {
+            return false;
+        };
+        let spot = match spot {
+            Spot::Column(x, _) => Spot::Column(x, fc.columns[x].0.len().saturating_sub(1)),
+            spot => spot,
+        };
+        let Some(n) = fc.run(spot).last().and_then(|card| fc.foundation_for(card)) else {
+            return false;
+        };
+        self.freecell_selected = None;
+        self.freecell_move(spot, Spot::Foundation(n))
+    }
+
+    fn freecell_card_rect(&self, fc: &FreeCell, spot: Spot) -> Rect {
+        let (w, h) = (self.card.width, self.card.height);
+        let (x, y) = match spot {
+            Spot::Cell(n) => (n as u16 * w, 0),
+            Spot::Foundation(n) => ((4 + n as u16) * w, 0),
+            Spot::Column(x, y) => (x as u16 * w, self.lower_card_y(&fc.columns[x].0, y)),
+        };
+        Rect::new(x, y, w, h)
+    }
+
+    // board-relative cell to the spot under it
+    fn freecell_spot(&self, x: u16, y: u16) -> Option<Spot> {
+        let fc = self.freecell.as_ref()?;
+        let slot = (x / self.card.width) as usize;
+        if slot >= 8 {
+            return None;
+        }
+        if y < self.card.height {
+            return Some(if slot < 4 {Spot::Cell(slot)} else {Spot::Foundation(slot - 4)});
+        }
+        Some(Spot::Column(slot, self.lower_card_at(&fc.columns[slot].0, y)?))
+    }
+
+    fn render_freecell(&self, fc: &FreeCell, area: Rect, buf: &mut Buffer) {
+        let key = |action| self.keymap.label(action);
+        let status = format!("{} undo  {} new game  {} quit", key(Action::Undo), key(Action::NewGame), key(Action::Quit));
+        let panel = [
+            format!("Time {}", self.clock()),
+            self.seed_label(fc.seed),
+            format!("Moves {}", fc.moves),
+            format!("Runs of {} can move", fc.max_run(false)),
+        ];
+        let area = self.render_side(status, &panel, area, buf);
+        let on_area = |rect: Rect| on_board(rect, area);
+
+        for (n, cell) in fc.cells.iter().enumerate() {
+            let rect = on_area(self.freecell_card_rect(fc, Spot::Cell(n)));
+            Themed(&Pile(cell.iter().copied().collect()), &self.theme).render(rect, buf);
+        }
+        for (n, pile) in fc.foundations.iter().enumerate() {
+            let rect = on_area(self.freecell_card_rect(fc, Spot::Foundation(n)));
+            let gone = self.cascade.as_ref().map_or(0, |c| c.taken(n));
+            Themed(&Pile(pile.0[..pile.0.len() - gone].to_vec()), &self.theme).render(rect, buf);
+            if pile.0.is_empty() {
+                self.render_suit_mark(n, rect, buf);
+            }
+        }
+        for (x, col) in fc.columns.iter().enumerate() {
+            let rect = on_area(self.freecell_card_rect(fc, Spot::Column(x, 0)));
+            Fanned(col, &self.theme, &self.lower_steps(&col.0)).render(rect, buf);
+        }
+
+        if let Some(spot) = self.freecell_selected {
+            let card = self.freecell_card_rect(fc, spot);
+            let label = Rect::new(card.x + 1, card.y + 1, card.width - 2, 1);
+            buf.set_style(on_area(label), self.theme.selected);
+        }
+        self.render_cascade(area, buf);
+
+        if self.state == AppState::ConfirmQuit {
+            self.render_popup("Quit? (y/n)", Alignment::Center, area, buf);
+        }
+    }
+}
+
+// Spider: the stock and the cleared runs across the top, the ten columns below them
+impl App {
+    fn new_spider(&mut self, seed: Option<u64>) {
+        self.end_game();
+        self.spider = Some(Spider::new(seed.unwrap_or_else(rand::random)));
+        self.recorded = false;
+        self.spider_selected = None;
+        self.notice = None;
+        self.start = Instant::now();
+    }
+
+    fn spider_key(&mut self, code: KeyCode) {
+        if let Some(action) = self.keymap.action(code) {
+            self.spider_perform(action);
+        }
+    }
+
+    fn spider_perform(&mut self, action: Action) {
+        let Some(sp) = &mut self.spider else {
+            return;
+        };
+        self.notice = None;
+        match action {
+            Action::Draw => {
+                self.spider_selected = None;
+                if let Err(err) = sp.deal() {
+                    self.notice = Some(if err == MoveError::ColumnEmpty {"Fill every column before dealing"} else {"The stock is empty"});
+                }
+            }
+            Action::Undo => {
+                self.spider_selected = None;
+                sp.undo();
+            }
+            Action::NewGame => self.new_spider(None),
+            Action::ClearSelection => self.spider_selected = None,
+            Action::Quit => self.quit(),
+            _ => {}
+        }
+        self.spider_won();
+    }
+
+    // acts on the release like the Klondike board does, so a press can still become a drag
+    fn spider_mouse(&mut self, ev: event::MouseEvent) {
+        let Some((x, y)) = self.board_point(ev) else {
+            self.pressed = None;
+            return;
+        };
+        match ev.kind {
+            MouseEventKind::Down(event::MouseButton::Left) => self.pressed = Some((x, y)),
+            MouseEventKind::Up(event::MouseButton::Left) => self.spider_release(x, y),
+            _ => {}
+        }
+    }
+
+    // the column and card at a board cell below the top row
+    fn spider_spot(&self, x: u16, y: u16) -> Option<(usize, usize)> {
+        let slot = (x / self.card.width) as usize;
+        if slot >= 10 || y < self.card.height {
+            return None;
+        }
+        Some((slot, self.lower_card_at(&self.spider.as_ref()?.columns[slot].0, y)?))
+    }
+
+    // a drag that ends on another column is a move, anything else counts as a click
+    fn spider_release(&mut self, x: u16, y: u16) {
+        let pressed = self.pressed.take();
+        let slot = (x / self.card.width) as usize;
+        if slot >= 10 {
+            return;
+        }
+        self.notice = None;
+        if y < self.card.height {
+            // the stock sits in the first slot
+            if slot == 0 {
+                self.spider_perform(Action::Draw);
+            }
+            return;
+        }
+        let Some((_, card)) = self.spider_spot(x, y) else {
+            return;
+        };
+        if let Some(src) = pressed.filter(|&at| at != (x, y)).and_then(|(x, y)| self.spider_spot(x, y)) {
+            if src.0 != slot {
+                self.spider_selected = None;
+                self.spider_move(src, slot);
+                return;
+            }
+        }
+        match self.spider_selected.take() {
+            Some((x, _)) if x == slot => {}
+            Some(src) => {
+                if !self.spider_move(src, slot) {
+                    self.spider_selected = Some((slot, card));
+                }
+            }
+            None => self.spider_selected = Some((slot, card)),
+        }
+        if let Some(sp) = &self.spider {
+            self.spider_selected = self.spider_selected.filter(|&(x, y)| !sp.run(x, y).is_empty());
+        }
+    }
+
+    fn spider_move(&mut self, src: (usize, usize), to: usize) -> bool {
+        let Some(sp) = &mut self.spider else {
+            return false;
+        };
+        if let Err(err) = sp.apply(src, to) {
+            if self.verbose {
+                self.log(format!("{:?} -> {}: {}", src, to, err));
+            }
+            return false;
+        }
+        self.spider_won();
+        true
+    }
+
+    fn spider_won(&mut self) {
+        if self.spider.as_ref().is_some_and(Spider::check_win) {
+            self.won();
+        }
+    }
+
+    fn render_spider(&self, sp: &Spider, area: Rect, buf: &mut Buffer) {
+        let key = |action| self.keymap.label(action);
+        let status = format!(
+            "{} deal  {} undo  {} new game  {} quit",
+            key(Action::Draw), key(Action::Undo), key(Action::NewGame), key(Action::Quit)
+        );
+        let panel = [
+            format!("Time {}", self.clock()),
+            self.seed_label(sp.seed),
+            format!("Moves {}", sp.moves),
+            format!("Deals left {}", sp.deals_left()),
+            format!("Runs cleared {}/8", sp.completed.len()),
+        ];
+        let area = self.render_side(status, &panel, area, buf);
+        let (w, h) = (self.card.width, self.card.height);
+        let on_area = |rect: Rect| on_board(rect, area);
+
+        Themed(&sp.stock, &self.theme).render(on_area(Rect::new(0, 0, w, h)), buf);
+        // a king for every run cleared, from the third slot on
+        for (i, king) in sp.completed.iter().enumerate() {
+            Themed(king, &self.theme).render(on_area(Rect::new((2 + i as u16) * w, 0, w, h)), buf);
+        }
+        for (x, col) in sp.columns.iter().enumerate() {
+            let rect = on_area(Rect::new(x as u16 * w, h + 1, w, h));
+            Fanned(col, &self.theme, &self.lower_steps(&col.0)).render(rect, buf);
+        }
+
+        if let Some((x, y)) = self.spider_selected {
+            let label = Rect::new(x as u16 * w + 1, self.lower_card_y(&sp.columns[x].0, y) + 1, w - 2, 1);
+            buf.set_style(on_area(label), self.theme.selected);
+        }
+
+        if let Some(notice) = self.notice {
+            Span::styled(notice, Style::new().yellow())
+                .render(on_area(Rect::new(self.board_width() + 1, 6, area.width, 1)), buf);
+        }
+
+        if self.state == AppState::ConfirmQuit {
+            self.render_popup("Quit? (y/n)", Alignment::Center, area, buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyModifiers, MouseButton, MouseEvent};
+    use game::fixtures::*;
+
+    // an app whose deal has every pile emptied, to lay out positions by hand
+    fn bare_app() -> App {
+        let mut app = App::init(Some(1));
+        clear_piles(&mut app.game);
+        app
+    }
+
+    #[test]
+    fn auto_stock_deals_until_a_move_turns_up() {
+        let mut app = bare_app();
+        app.auto_stock = true;
+        app.game.rows[0].0 = vec![card(12, 0)];
+        // the last card is dealt first: 5♣ goes nowhere, Q♥ goes on the K♠
+        app.game.stock.0 = vec![face_down(11, 1), face_down(4, 2)];
+
+        app.auto_advance();
+        assert!(!app.stalemate);
+        assert_eq!(app.game.discard.0.last().map(|c| (c.number, c.suit)), Some((11, 1)));
+    }
+
+    #[test]
+    fn auto_stock_gives_up_after_a_full_pass() {
+        let mut app = bare_app();
+        app.auto_stock = true;
+        app.game.rows[0].0 = vec![card(12, 0)];
+        app.game.stock.0 = vec![face_down(6, 3), face_down(4, 2)];
+
+        app.auto_advance();
+        assert!(app.stalemate);
+        assert!(app.game.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn autocomplete_undoes_in_one_press() {
+        let mut app = bare_app();
+        app.game.rows[0].0 = vec![card(1, 0), card(0, 0)];
+        app.game.stock.0 = vec![face_down(2, 0)];
+        let before = app.game.state_hash();
+
+        app.perform(Action::Autocomplete);
+        while app.autocomplete.is_some() {
+            app.autocomplete_step();
+        }
+        assert_eq!(app.game.suit_piles[0].0.len(), 3);
+
+        app.undo();
+        assert_eq!(app.game.state_hash(), before);
+        assert!(app.game.undo_stack.is_empty());
+    }
+
+    #[test]
+    fn autocomplete_waits_for_a_yes_when_asked_to() {
+        let mut app = bare_app();
+        app.confirm_autocomplete = true;
+        app.game.rows[0].0 = vec![card(0, 0)];
+
+        app.perform(Action::Autocomplete);
+        assert_eq!(app.state, AppState::AutocompletePrompt);
+        assert!(app.autocomplete.is_none());
+
+        app.handle_event(Event::Key(KeyCode::Char('n').into()));
+        assert_eq!(app.state, AppState::Playing);
+        assert!(app.autocomplete.is_none());
+
+        app.perform(Action::Autocomplete);
+        app.handle_event(Event::Key(KeyCode::Char('y').into()));
+        assert!(app.autocomplete.is_some());
+    }
+
+    #[test]
+    fn undo_button_takes_back_the_last_move() {
+        let mut app = bare_app();
+        app.game.rows[0].0 = vec![card(0, 0)];
+        let before = app.game.state_hash();
+        app.game.apply(SelectedPos::Column(0, 0), SelectedPos::SuitPile(0)).unwrap();
+
+        let (_, rect) = app.buttons().into_iter().find(|&(b, _)| b == Button::Undo).unwrap();
+        assert_eq!(app.button_at(rect.x, rect.y), Some(Button::Undo));
+        app.press(Button::Undo);
+        assert_eq!(app.game.state_hash(), before);
+        assert_eq!(app.state, AppState::Playing);
+    }
+
+    // a left click, press and release, on the card at pos of the card at pos, in screen coordinates
+    fn click_on(app: &mut App, pos: SelectedPos) {
+        let board = app.layout(app.screen);
+        let margin = app.theme.margin();
+        let rect = app.card_rect(pos).unwrap();
+        for kind in [MouseEventKind::Down(MouseButton::Left), MouseEventKind::Up(MouseButton::Left)] {
+            app.handle_event(Event::Mouse(MouseEvent {
+                kind,
+                column: board.x + margin + rect.x + rect.width / 2,
+                row: board.y + margin + rect.y + 1,
+                modifiers: KeyModifiers::NONE,
+            }));
+        }
+    }
+
+    #[test]
+    fn clicks_are_ignored_while_collect_is_offered() {
+        let mut app = bare_app();
+        app.screen = Rect::new(0, 0, 120, 50);
+        app.game.rows[0].0 = vec![card(0, 0)];
+        app.state = AppState::CollectPrompt;
+        let before = app.game.state_hash();
+
+        click_on(&mut app, SelectedPos::Column(0, 0));
+        assert_eq!(app.state, AppState::CollectPrompt);
+        assert_eq!(app.selected_pos, SelectedPos::None);
+        assert_eq!(app.game.state_hash(), before);
+    }
+
+    #[test]
+    fn clicking_the_selected_card_again_deselects_it() {
+        let mut app = bare_app();
+        app.game.rows[0].0 = vec![face_down(3, 2), card(12, 0), card(11, 1)];
+        app.game.rows[1].0 = vec![card(5, 3)];
+        let before = app.game.state_hash();
+
+        app.screen = Rect::new(0, 0, 120, 50);
+        // quick enough to count as a double-click, on cards no foundation takes
+        for pos in [SelectedPos::Column(0, 1), SelectedPos::Column(0, 2)] {
+            click_on(&mut app, pos);
+            assert_eq!(app.selected_pos, pos);
+            click_on(&mut app, pos);
+            assert_eq!(app.selected_pos, SelectedPos::None);
+        }
+        assert_eq!(app.game.state_hash(), before);
+        assert_eq!(app.game.moves, 0);
+    }
+
+    #[test]
+    fn renders_into_tiny_areas_without_panicking() {
+        let app = App::init(Some(1));
+        for (w, h) in [(0, 0), (1, 1), (0, 5), (5, 0)] {
+            let area = Rect::new(0, 0, w, h);
+            let mut buf = Buffer::empty(area);
+            (&app).render(area, &mut buf);
+        }
+    }
+
+    #[test]
+    fn deal_and_play_sends_a_dealt_ace_home() {
+        let mut app = bare_app();
+        app.game.rows[0].0 = vec![card(12, 0)];
+        app.game.stock.0 = vec![face_down(4, 2), face_down(0, 1)];
+
+        app.handle_event(Event::Key(KeyCode::Char('f').into()));
+        assert_eq!(faces(&app.game.suit_piles[1].0), [(0, 1)]);
+        assert!(app.game.discard.0.is_empty());
+        assert_eq!(app.game.stock.0.len(), 1);
+
+        app.undo();
+        assert_eq!(faces(&app.game.stock.0), [(4, 2), (0, 1)]);
+        assert!(app.game.stock.0.iter().all(|c| c.hidden));
+        assert!(app.game.suit_piles[1].0.is_empty());
+        assert!(app.game.undo_stack.is_empty());
+    }
+
+    // J♥ can go on the Q♠ and nothing else moves; with it there, the stock's
+    // 3♦ goes nowhere and the 6♥ under it onto the 7♣
+    fn one_move_from_dead(app: &mut App) {
+        app.auto_stock = true;
+        app.game.rows[0].0 = vec![card(11, 0)];
+        app.game.rows[1].0 = vec![card(10, 1)];
+        for (x, top) in [(2, card(4, 2)), (3, card(4, 0)), (4, card(6, 2)), (5, card(6, 0)), (6, card(8, 2))] {
+            app.game.rows[x].0 = vec![face_down(12, 1), top];
+        }
+        app.game.stock.0 = vec![face_down(5, 1), face_down(2, 3)];
+    }
+
+    #[test]
+    fn auto_stock_deals_are_undone_with_the_move_before_them() {
+        let mut app = bare_app();
+        one_move_from_dead(&mut app);
+        let before = app.game.state_hash();
+
+        app.try_move(SelectedPos::Column(1, 0), SelectedPos::Column(0, 0));
+        app.auto_advance();
+        assert_eq!(faces(&app.game.discard.0), [(2, 3), (5, 1)]);
+        assert_eq!(app.game.undo_stack.len(), 1);
+
+        app.undo();
+        assert_eq!(app.game.state_hash(), before);
+        assert_eq!(app.game.score, -app.undo_penalty);
+        app.auto_advance();
+        assert_eq!(app.game.state_hash(), before);
+    }
+
+    #[test]
+    fn deals_before_the_first_move_cannot_be_undone() {
+        let mut app = bare_app();
+        one_move_from_dead(&mut app);
+        let jack = app.game.rows[1].0.pop().unwrap();
+        app.game.rows[0].0.push(jack);
+
+        app.auto_advance();
+        assert_eq!(app.game.discard.0.len(), 2);
+        let dealt = app.game.state_hash();
+        app.undo();
+        assert_eq!(app.game.state_hash(), dealt);
+        assert_eq!(app.game.score, 0);
+    }
+
+    #[test]
+    fn stale_discard_selection_is_dropped() {
+        // neither destination holds a card the click could pick up instead
+        for dest in [SelectedPos::SuitPile(0), SelectedPos::Column(1, 0)] {
+            let mut app = bare_app();
+            app.game.rows[0].0 = vec![card(12, 0)];
+            let before = app.game.state_hash();
+            app.selected_pos = SelectedPos::Discard;
+
+            app.click(dest);
+            assert_eq!(app.selected_pos, SelectedPos::None);
+            assert_eq!(app.game.state_hash(), before);
+        }
+    }
+
+    #[test]
+    fn restart_puts_back_the_dealt_board() {
+        let mut app = App::init(Some(7));
+        let dealt = app.game.state_hash();
+        app.game.deal();
+        if let Some((src, dest)) = app.game.legal_moves().first().copied() {
+            app.try_move(src, dest);
+        }
+        assert_ne!(app.game.state_hash(), dealt);
+
+        app.handle_event(Event::Key(KeyCode::Char('r').into()));
+        app.finish_dealing();
+        assert_eq!(app.game.state_hash(), dealt);
+        assert_eq!((app.game.moves, app.game.score), (0, 0));
+    }
+
+    #[test]
+    fn restart_after_loading_uses_the_saved_deal() {
+        let path = std::env::temp_dir().join(format!("solitui_restart_{}", std::process::id()));
+        let mut app = App::init(Some(7));
+        let dealt = app.game.state_hash();
+        app.game.deal();
+        // as if dealt by a version whose seeds dealt other boards
+        app.game.seed = 8;
+        app.dealt.as_mut().unwrap().seed = 8;
+        app.save(&path).unwrap();
+
+        let mut loaded = App::init(Some(1));
+        loaded.load(&path).unwrap();
+        loaded.press(Button::Restart);
+        assert_eq!(loaded.game.state_hash(), dealt);
+
+        // a save from before the deal was kept can't be restarted, and says so
+        let old: String = fs::read_to_string(&path).unwrap().lines()
+            .filter(|line| !line.starts_with("dealt_"))
+            .map(|line| format!("{line}\n"))
+            .collect();
+        fs::write(&path, old).unwrap();
+        loaded.load(&path).unwrap();
+        let saved = loaded.game.state_hash();
+        loaded.press(Button::Restart);
+        assert_eq!(loaded.game.state_hash(), saved);
+        assert!(loaded.notice.is_some());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn hit_test_finds_the_card_in_each_strip() {
+        let mut app = bare_app();
+        let (w, h) = (app.card.width as usize, app.card.height as usize);
+        app.game.rows[0].0 = vec![card(12, 0)];
+        app.game.rows[1].0 = vec![card(12, 1), card(11, 0), card(10, 1)];
+        app.game.rows[2].0 = vec![face_down(1, 1), face_down(2, 1), face_down(3, 1), card(9, 0), card(8, 1), card(7, 0), card(6, 1)];
+
+        for y in [0, h - 1] {
+            assert_eq!(app.hit_test(1, y), SelectedPos::Column(0, 0));
+        }
+        for (y, card) in [(0, 0), (1, 0), (2, 1), (3, 1), (4, 2), (4 + h - 1, 2)] {
+            assert_eq!(app.hit_test(w + 1, y), SelectedPos::Column(1, card));
+        }
+        // a face-down card is a click on the column, not on that card
+        for (y, card) in [(0, 0), (5, 0), (6, 3), (8, 4), (11, 5), (12, 6), (12 + h - 1, 6)] {
+            assert_eq!(app.hit_test(2 * w + w - 1, y), SelectedPos::Column(2, card));
+        }
+    }
+
+    #[test]
+    fn clicks_are_ignored_while_help_is_open() {
+        let mut app = bare_app();
+        app.screen = Rect::new(0, 0, 120, 50);
+        app.game.rows[0].0 = vec![card(12, 0)];
+        app.state = AppState::Help;
+
+        click_on(&mut app, SelectedPos::Column(0, 0));
+        assert_eq!(app.state, AppState::Help);
+        assert_eq!(app.selected_pos, SelectedPos::None);
+    }
+
+    #[test]
+    fn custom_keymap_routes_its_keys() {
+        let map = KeyMap::parse("draw = x\nundo=f5\nbogus=q\nquit=not a key\n");
+        assert_eq!(map.action(KeyCode::Char('x')), Some(Action::Draw));
+        assert_eq!(map.action(KeyCode::F(5)), Some(Action::Undo));
+        assert_eq!(map.action(KeyCode::Char('d')), None);
+        assert_eq!(map.action(KeyCode::Char('q')), None);
+        assert_eq!(map.action(KeyCode::Esc), Some(Action::Quit));
+
+        let mut app = bare_app();
+        app.game.stock.0 = vec![face_down(4, 2)];
+        app.keymap = map;
+        app.handle_event(Event::Key(KeyCode::Char('x').into()));
+        assert_eq!(faces(&app.game.discard.0), [(4, 2)]);
+        app.handle_event(Event::Key(KeyCode::F(5).into()));
+        assert!(app.game.discard.0.is_empty());
+    }
+
+    #[test]
+    fn default_cards_keep_the_minimum_board() {
+        let card = CardSize::default();
+        // seven columns, a gap, then the piles, which stand six cards tall
+        assert_eq!(card.piles_x(), 36);
+        assert_eq!(card.board_width(), 41);
+        assert_eq!(card.board_height(), 31);
+        assert!(CardSize::MIN.board_width() <= card.board_width());
+    }
+
+    #[test]
+    fn selection_left_past_a_shrunk_column_is_dropped() {
+        let mut app = bare_app();
+        app.game.rows[0].0 = vec![card(12, 0), card(11, 1), card(10, 0), card(9, 1)];
+        app.game.rows[1].0 = vec![card(12, 2)];
+
+        app.click(SelectedPos::Column(0, 2));
+        assert_eq!(app.selected_pos, SelectedPos::Column(0, 2));
+        // the Q♥ run moves off under the selection, leaving the K♠ alone
+        app.game.apply(SelectedPos::Column(0, 1), SelectedPos::Column(1, 0)).unwrap();
+        let before = app.game.state_hash();
+        let moves = app.game.moves;
+
+        app.click(SelectedPos::Column(3, 0));
+        assert_eq!(app.selected_pos, SelectedPos::None);
+        assert_eq!(app.game.state_hash(), before);
+        assert_eq!(app.game.moves, moves);
+        assert_eq!(faces(&app.game.rows[0].0), [(12, 0)]);
+        assert_eq!(app.game.rows[1].0.len(), 4);
+    }
+
+    #[test]
+    fn undoing_a_foundation_move_costs_the_penalty() {
+        for penalty in [UNDO_PENALTY, 0] {
+            let mut app = bare_app();
+            app.undo_penalty = penalty;
+            app.game.rows[0].0 = vec![card(5, 2), card(0, 0)];
+
+            app.try_move(SelectedPos::Column(0, 1), SelectedPos::SuitPile(0));
+            assert_eq!(app.game.score, 10);
+            app.undo();
+            assert_eq!(app.game.score, -penalty);
+        }
+        assert_eq!(UNDO_PENALTY, 2);
+    }
+
+    #[test]
+    fn command_line_options_are_not_saved_with_a_changed_pref() {
+        let path = std::env::temp_dir().join(format!("solitui_prefs_{}", std::process::id()));
+        let mut app = bare_app();
+        app.prefs_file = Some(path.clone());
+        // as if started with --draw 3 --vegas over the saved defaults
+        app.game.draw_count = 3;
+        app.scoring = ScoringMode::Vegas;
+
+        app.handle_event(Event::Key(KeyCode::Char('T').into()));
+        let saved = Prefs::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(saved, Prefs { light: !Prefs::default().light, ..Prefs::default() });
+    }
+
+    // a press or release of the left button on a FreeCell spot, in screen coordinates
+    fn mouse_on_freecell(app: &mut App, kind: MouseEventKind, spot: Spot) {
+        let board = app.layout(app.screen);
+        let margin = app.theme.margin();
+        let rect = app.freecell_card_rect(app.freecell.as_ref().unwrap(), spot);
+        app.handle_event(Event::Mouse(MouseEvent {
+            kind,
+            column: board.x + margin + rect.x + rect.width / 2,
+            row: board.y + margin + rect.y + 1,
+            modifiers: KeyModifiers::NONE,
+        }));
+    }
+
+    #[test]
+    fn freecell_clicks_act_on_release_and_its_win_is_counted() {
+        let mut app = bare_app();
+        app.screen = Rect::new(0, 0, 120, 50);
+        let mut fc = FreeCell::new(1);
+        for (n, pile) in fc.foundations.iter_mut().enumerate() {
+            pile.0 = (0..13).map(|number| card(number, n as u8)).collect();
+        }
+        fc.columns = std::array::from_fn(|_| Column(Vec::new()));
+        fc.columns[0].0 = fc.foundations[3].0.pop().into_iter().collect();
+        app.freecell = Some(fc);
+        let king = Spot::Column(0, 0);
+
+        mouse_on_freecell(&mut app, MouseEventKind::Down(MouseButton::Left), king);
+        assert_eq!(app.freecell_selected, None);
+        mouse_on_freecell(&mut app, MouseEventKind::Up(MouseButton::Left), king);
+        assert_eq!(app.freecell_selected, Some(king));
+
+        // the second click sends the king home and wins
+        mouse_on_freecell(&mut app, MouseEventKind::Down(MouseButton::Left), king);
+        mouse_on_freecell(&mut app, MouseEventKind::Up(MouseButton::Left), king);
+        assert!(app.freecell.as_ref().unwrap().check_win());
+        assert_eq!(app.state, AppState::Celebrating);
+        assert_eq!((app.stats.played, app.stats.won), (1, 1));
+    }
+
+    // a press or release of the left button on card y of Spider column x, in screen coordinates
+    fn mouse_on_spider(app: &mut App, kind: MouseEventKind, x: usize, y: usize) {
+        let board = app.layout(app.screen);
+        let margin = app.theme.margin();
+        let row = app.lower_card_y(&app.spider.as_ref().unwrap().columns[x].0, y);
+        app.handle_event(Event::Mouse(MouseEvent {
+            kind,
+            column: board.x + margin + x as u16 * app.card.width + app.card.width / 2,
+            row: board.y + margin + row + 1,
+            modifiers: KeyModifiers::NONE,
+        }));
+    }
+
+    #[test]
+    fn spider_clicks_act_on_release_and_its_win_is_counted() {
+        let mut app = bare_app();
+        app.screen = Rect::new(0, 0, 120, 50);
+        let mut sp = Spider::new(1);
+        sp.columns = std::array::from_fn(|_| Column(Vec::new()));
+        sp.columns[0].0 = (1..13).rev().map(|n| card(n, 0)).collect();
+        sp.columns[1].0 = vec![card(0, 0)];
+        sp.completed = vec![card(12, 0); 7];
+        app.spider = Some(sp);
+
+        mouse_on_spider(&mut app, MouseEventKind::Down(MouseButton::Left), 1, 0);
+        assert_eq!(app.spider_selected, None);
+        mouse_on_spider(&mut app, MouseEventKind::Up(MouseButton::Left), 1, 0);
+        assert_eq!(app.spider_selected, Some((1, 0)));
+
+        // putting the ace on the two finishes the eighth run
+        mouse_on_spider(&mut app, MouseEventKind::Down(MouseButton::Left), 0, 11);
+        mouse_on_spider(&mut app, MouseEventKind::Up(MouseButton::Left), 0, 11);
+        assert!(app.spider.as_ref().unwrap().check_win());
+        assert_eq!(app.state, AppState::Won);
+        assert_eq!((app.stats.played, app.stats.won), (1, 1));
+    }
+
+    #[test]
+    fn the_side_panel_shows_the_last_move_and_the_stock() {
+        let mut app = bare_app();
+        app.screen = Rect::new(0, 0, 120, 50);
+        app.game.rows[0].0 = vec![card(12, 0)];
+        app.game.rows[1].0 = vec![card(11, 1)];
+        app.game.stock.0 = vec![face_down(4, 2), face_down(0, 1)];
+        let panel = |app: &App| {
+            let mut buf = Buffer::empty(app.screen);
+            app.render(app.screen, &mut buf);
+            buf.content.iter().map(|c| c.symbol()).collect::<String>()
+        };
+
+        app.try_move(SelectedPos::Column(1, 0), SelectedPos::Column(0, 0));
+        assert!(panel(&app).contains("Last Q♥ to column 1"));
+        assert!(panel(&app).contains("Stock 2"));
+        app.undo();
+        assert!(!panel(&app).contains("Last"));
+    }
+}
@@ -0,0 +1,236 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::game::{Card, Column, Deck, MoveError, Pile};
+
+// one undoable piece of a player's action, a move can be followed by a clear
+enum Step {
+    Move { from: usize, to: usize, count: usize, revealed: bool },
+    Deal,
+    Clear { column: usize, revealed: bool },
+}
+
+// one-suit Spider: ten columns from two decks of spades, with a full king-to-ace run
+// leaving the board as soon as it is put together
+pub struct Spider {
+    pub columns: [Column; 10],
+    pub stock: Pile,
+    // the king of every run cleared so far
+    pub completed: Vec<Card>,
+    pub seed: u64,
+    pub moves: u32,
+    // each action's steps in the order they happened
+    history: Vec<Vec<Step>>,
+}
+
+// face-up and running down by one in a single suit, as a Spider run must be to move
+fn is_suit_run(run: &[Card]) -> bool {
+    run.iter().all(|c| !c.hidden) &&
+    run.windows(2).all(|w| w[0].suit == w[1].suit && w[0].number == w[1].number + 1)
+}
+
+impl Spider {
+    pub fn new(seed: u64) -> Self {
+        let mut deck = Deck::spider(&mut StdRng::seed_from_u64(seed));
+        // 54 cards out, six to each of the first four columns and five to the rest
+        let columns = std::array::from_fn(|i| {
+            let mut col = Column(deck.deal(if i < 4 {6} else {5}));
+            col.0.last_mut().unwrap().hidden = false;
+            col
+        });
+
+        Self {
+            columns,
+            stock: Pile(deck.deal(deck.len())),
+            completed: Vec::new(),
+            seed,
+            moves: 0,
+            history: Vec::new(),
+        }
+    }
+
+    // the run picked up at card y of column x, empty if it can't be moved as one
+    pub fn run(&self, x: usize, y: usize) -> &[Card] {
+        let run = self.columns[x].0.get(y..).unwrap_or(&[]);
+        if is_suit_run(run) {run} else {&[]}
+    }
+
+    pub fn apply(&mut self, (sx, sy): (usize, usize), dx: usize) -> Result<(), MoveError> {
+        if sx == dx {
+            return Err(MoveError::SameColumn);
+        }
+        let Some(&card) = self.columns[sx].0.get(sy) else {
+            return Err(MoveError::EmptySource);
+        };
+        if self.run(sx, sy).is_empty() {
+            return Err(MoveError::BrokenRun);
+        }
+        // any suit will do to build on, only moving needs the suits to match
+        if self.columns[dx].0.last().is_some_and(|top| top.number != card.number + 1) {
+            return Err(MoveError::ColumnMismatch);
+        }
+        let cards = self.columns[sx].0.split_off(sy);
+        let count = cards.len();
+        self.columns[dx].0.extend(cards);
+        let revealed = self.reveal_top(sx);
+        let mut steps = vec![Step::Move { from: sx, to: dx, count, revealed }];
+        steps.extend(self.clear_run(dx));
+        self.history.push(steps);
+        self.moves += 1;
+        Ok(())
+    }
+
+    // a card from the stock onto every column, only once none of them is empty
+    pub fn deal(&mut self) -> Result<(), MoveError> {
+        if self.stock.0.is_empty() {
+            return Err(MoveError::EmptySource);
+        }
+        if self.columns.iter().any(|c| c.0.is_empty()) {
+            return Err(MoveError::ColumnEmpty);
+        }
+        for col in &mut self.columns {
+            let mut card = self.stock.0.pop().unwrap();
+            card.hidden = false;
+            col.0.push(card);
+        }
+        let mut steps = vec![Step::Deal];
+        for x in 0..10 {
+            steps.extend(self.clear_run(x));
+        }
+        self.history.push(steps);
+        Ok(())
+    }
+
+    // takes a finished king-to-ace run off the end of column x
+    fn clear_run(&mut self, x: usize) -> Option<Step> {
+        let col = &mut self.columns[x].0;
+        let start = col.len().checked_sub(13)?;
+        if col[start].number != 12 || !is_suit_run(&col[start..]) {
+            return None;
+        }
+        self.completed.push(col[start]);
+        col.truncate(start);
+        let revealed = self.reveal_top(x);
+        Some(Step::Clear { column: x, revealed })
+    }
+
+    fn reveal_top(&mut self, x: usize) -> bool {
+        match self.columns[x].0.last_mut() {
+            Some(top) if top.hidden => {
+                top.hidden = false;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn hide_top(&mut self, x: usize) {
+        if let Some(top) = self.columns[x].0.last_mut() {
+            top.hidden = true;
+        }
+    }
+
+    pub fn undo(&mut self) {
+        let Some(steps) = self.history.pop() else {
+            return;
+        };
+        // deals aren't counted as moves, so only a move gives one back
+        if matches!(steps.first(), Some(Step::Move { .. })) {
+            self.moves -= 1;
+        }
+        for step in steps.into_iter().rev() {
+            match step {
+                Step::Move { from, to, count, revealed } => {
+                    if revealed {
+                        self.hide_top(from);
+                    }
+                    let col = &mut self.columns[to].0;
+                    let cards = col.split_off(col.len() - count);
+                    self.columns[from].0.extend(cards);
+                }
+                Step::Deal => {
+                    for col in self.columns.iter_mut().rev() {
+                        let mut card = col.0.pop().unwrap();
+                        card.hidden = true;
+                        self.stock.0.push(card);
+                    }
+                }
+                Step::Clear { column, revealed } => {
+                    if revealed {
+                        self.hide_top(column);
+                    }
+                    let king = self.completed.pop().unwrap();
+                    self.columns[column].0.extend((0..13).rev().map(|number| Card { number, ..king }));
+                }
+            }
+        }
+    }
+
+    // deals left in the stock
+    pub fn deals_left(&self) -> usize {
+        self.stock.0.len() / 10
+    }
+
+    pub fn check_win(&self) -> bool {
+        self.completed.len() == 8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures::{card, face_down};
+
+    #[test]
+    fn a_finished_run_leaves_the_board() {
+        let mut game = Spider::new(1);
+        let under = face_down(4, 0);
+        game.columns[0].0 = std::iter::once(under).chain((1..13).rev().map(|n| card(n, 0))).collect();
+        game.columns[1].0 = vec![card(7, 0), card(0, 0)];
+
+        assert_eq!(game.apply((1, 1), 0), Ok(()));
+        assert_eq!(game.completed.len(), 1);
+        assert_eq!(game.completed[0].number, 12);
+        assert_eq!(game.columns[0].0.len(), 1);
+        assert!(!game.columns[0].0[0].hidden);
+        assert_eq!(game.moves, 1);
+
+        // the run comes back along with the ace, and the card under it turns back over
+        game.undo();
+        assert!(game.completed.is_empty());
+        assert_eq!(game.columns[0].0.len(), 13);
+        assert!(game.columns[0].0[0].hidden);
+        assert_eq!(game.columns[1].0.len(), 2);
+        assert_eq!(game.moves, 0);
+    }
+
+    #[test]
+    fn a_deal_puts_a_card_on_every_column() {
+        let mut game = Spider::new(1);
+        let lens: Vec<usize> = game.columns.iter().map(|c| c.0.len()).collect();
+        assert_eq!(game.deals_left(), 5);
+
+        assert_eq!(game.deal(), Ok(()));
+        for (col, len) in game.columns.iter().zip(&lens) {
+            assert_eq!(col.0.len(), len + 1);
+            assert!(!col.0.last().unwrap().hidden);
+        }
+        assert_eq!(game.deals_left(), 4);
+
+        game.undo();
+        assert!(game.columns.iter().map(|c| c.0.len()).eq(lens.iter().copied()));
+        assert_eq!(game.deals_left(), 5);
+        assert!(game.stock.0.iter().all(|c| c.hidden));
+    }
+
+    #[test]
+    fn no_deal_while_a_column_is_empty() {
+        let mut game = Spider::new(1);
+        game.columns[3].0.clear();
+        assert_eq!(game.deal(), Err(MoveError::ColumnEmpty));
+        assert_eq!(game.deals_left(), 5);
+
+        game.stock.0.clear();
+        game.columns[3].0.push(card(5, 0));
+        assert_eq!(game.deal(), Err(MoveError::EmptySource));
+    }
+}